@@ -1,4 +1,4 @@
-use math_parser::test_api::test_duration;
+use math_parser::test_api::{test_duration, test_bool, test_list, test_text, test_echo, test_echoed_code, test_include_result, test_include_error, get_results_with_includes};
 use math_parser::test_api::{test_exponent, test_result_with_number_format};
 use math_parser::test_api::{test_compiles, test_result, test_error, test_date, test_no_error};
 use math_parser::errors::ErrorId;
@@ -13,6 +13,27 @@ fn test_numbers (){
 #[test]
 fn test_constants (){
     test_result("trunc(PI)", 3.0, "");
+    test_result("const g0 = 9.81; g0*2;", 19.62, "");
+    test_result("const a = 2; { const b = 3; b; }", 3.0, "");
+    test_result("#define physics\ntrunc(e*1000);", 2718.0, "");
+    test_result("#define physics\ntau/PI;", 2.0, "");
+    test_result("#define physics\nphi^2 - phi;", 1.0, "");
+    test_result("#define physics\nc/1000;", 299792.458, "m/s");
+    test_result("#define physics\nG*1e11;", 6.6743, "m3/(kg*s2)");
+    test_result("#define physics\nh*1e34;", 6.6260702, "J*s");
+    test_result("#define physics\nk_B*1e23;", 1.380649, "J/K");
+    test_result("#define physics\nN_A/1e23;", 6.0221408, "/mol");
+    test_error("#define physics\nc + 1m;", ErrorId::UnitPropDiff);
+    test_error("#define physics\n#undef physics\nc;", ErrorId::VarNotDef);
+    test_no_error("phi = 30; e = 2; phi + e;");
+    test_error("e;", ErrorId::VarNotDef);
+    test_error("#define physics\n#undef physics\ne;", ErrorId::VarNotDef);
+    test_error("const x = 1; x = 2;", ErrorId::WConstRedef);
+    test_error("const x = 1; const x = 2;", ErrorId::WConstRedef);
+    test_error("#define strict\nconst x = 1; x = 2;", ErrorId::ConstRedef);
+    test_error("#define physics strict\nc = 2;", ErrorId::ConstRedef);
+    test_error("const x = 5; function f() { x = 7; x; } f();", ErrorId::WConstRedef);
+    test_error("const x += 1;", ErrorId::Expected);
 }
 
 #[test]
@@ -96,6 +117,11 @@ fn test_implicit_mult () {
     test_result("a=2; a(3(4a));", 48.0, "");
     test_result("m=2; 2m;", 4.0, "");
     test_error("m=2; 2m;", ErrorId::WUnitIsVar);
+    test_result("2(3)+1;", 8.0, "");
+    test_result("2(3)^2;", 18.0, "");
+    test_result("x=3; 2(x+1)^2;", 32.0, "");
+    test_result("max(2(3), 4);", 6.0, "");
+    test_list("2(1,2);", &[2.0, 4.0], "");
 }
 
 #[test]
@@ -455,3 +481,485 @@ dat+duur;
 }
 
 
+
+#[test]
+fn test_comparisons(){
+    test_bool("1 < 2", true);
+    test_bool("2 <= 2", true);
+    test_bool("3 > 4", false);
+    test_bool("3 >= 4", false);
+    test_bool("1+2 == 3", true);
+    test_bool("1+2 != 3", false);
+    test_bool("a=5; a < 10", true);
+    test_bool("10cm < 1m", true);
+    test_bool("1km == 1000m", true);
+    test_error("1kg < 1m", ErrorId::UnitPropDiff);
+    test_bool("'2022/01/31' < '2022/02/01'", true);
+    test_bool("'2022/01/31' == '2022/01/31'", true);
+    test_bool("'2023/03/01' - '2022/01/01' > '2022/03/01' - '2022/01/01'", true);
+    test_bool("(1 < 2) == (3 < 4)", true);
+    test_error("(1 < 2) < 3", ErrorId::NoOp);
+}
+
+#[test]
+fn test_conditionals(){
+    test_result("a=5; if (a > 3) { 10; } else { 20; }", 10.0, "");
+    test_result("a=1; if (a > 3) { 10; } else { 20; }", 20.0, "");
+    test_result("a=1; if (a > 3) { 10; } else if (a == 1) { 15; } else { 20; }", 15.0, "");
+    test_result("function sgn(x) { if (x < 0) { -1; } else { 1; } } sgn(-3)", -1.0, "");
+    test_result("a=1; x = a > 0 ? 100m : 200m;", 100.0, "m");
+    test_result("a=1; a < 0 ? 1 : a == 1 ? 2 : 3", 2.0, "");
+    test_no_error("a=1; a > 0 ? 1 : unknown_var");
+    test_no_error("a=1; if (a > 0) { 1; } else { unknown_var; }");
+    test_result("a=200; if (a > 100) { a*0.9; } else { a; }", 180.0, "");
+    test_result("a=2; if (a > 1) { b=2a; if (b > 3) { a+b; } }", 6.0, "");
+    test_result("a=2; if (a > 1) { a=5; } a", 2.0, "");
+    test_error("a=2; { a; }", ErrorId::VarNotDef);
+    test_error("if (1) { 2; }", ErrorId::CondNotBool);
+    test_error("1 ? 2 : 3", ErrorId::CondNotBool);
+    test_error("a = 1 > 0 ? 2", ErrorId::Expected);
+}
+
+#[test]
+fn test_ranges(){
+    test_list("1...5", &[1.0, 2.0, 3.0, 4.0, 5.0], "");
+    test_list("0...1 step 0.25", &[0.0, 0.25, 0.5, 0.75, 1.0], "");
+    test_list("0...1 step 0.1", &[0.0, 0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8, 0.9, 1.0], "");
+    test_list("3...1", &[3.0, 2.0, 1.0], "");
+    test_list("10...1 step -3", &[10.0, 7.0, 4.0, 1.0], "");
+    test_list("0m...1km step 250m", &[0.0, 250.0, 500.0, 750.0, 1000.0], "m");
+    test_list("0...30cm step 10", &[0.0, 10.0, 20.0, 30.0], "cm");
+    test_result("sum(1...100)", 5050.0, "");
+    test_date("max_date = last('2024/01/01'...'2024/01/20' step 7 days); max_date", 15, 1, Some(2024));
+    test_date("last('2024/01/01'...'2024/03/01' step 1 months)", 1, 3, Some(2024));
+    test_error("1...5 step -1", ErrorId::InvRange);
+    test_error("1...5 step 0", ErrorId::InvRange);
+    test_error("0...1e9", ErrorId::InvRange);
+    test_error("1...'2024/01/01'", ErrorId::InvRange);
+    test_error("'2024/01/01'...'2024/01/20' step 7", ErrorId::EExplicitUnitsExpected);
+}
+
+#[test]
+fn test_indexing(){
+    test_result("l=10,20,30,40; l[0]", 10.0, "");
+    test_result("l=10,20,30,40; l[3]", 40.0, "");
+    test_result("l=10,20,30,40; l[-1]", 40.0, "");
+    test_result("l=10,20,30,40; l[-4]", 10.0, "");
+    test_result("l=10m,20m; l[1].cm", 2000.0, "cm");
+    test_result("(1...5)[2]", 3.0, "");
+    test_list("l=10,20,30,40; l[1:3]", &[20.0, 30.0], "");
+    test_list("l=10,20,30,40; l[:2]", &[10.0, 20.0], "");
+    test_list("l=10,20,30,40; l[-2:]", &[30.0, 40.0], "");
+    test_list("l=10,20,30,40; l[2:100]", &[30.0, 40.0], "");
+    test_list("l=10,20,30,40; l[3:1]", &[], "");
+    test_error("l=10,20,30,40; l[4]", ErrorId::IndexOutOfRange);
+    test_error("l=10,20,30,40; l[-5]", ErrorId::IndexOutOfRange);
+    test_error("l=10,20,30,40; l[1.5]", ErrorId::InvIndex);
+    test_error("l=10,20,30,40; l[1m]", ErrorId::InvIndex);
+    test_error("a=3; a[0]", ErrorId::InvIndex);
+    test_error("l=10,20,30,40; l[0", ErrorId::Expected);
+}
+
+#[test]
+fn test_assign_to_parts(){
+    test_list("l=10,20,30; l[1]=5; l", &[10.0, 5.0, 30.0], "");
+    test_list("l=10,20,30; l[-1]+=5; l", &[10.0, 20.0, 35.0], "");
+    test_result("l=1m,2m; l[0].=cm; l[0]", 100.0, "cm");
+    test_result("l=(1,2),(3,4); l[1][0]=30; l[1][0]", 30.0, "");
+    test_result("d='2024/03/15'; d.year=2025; d.year", 2025.0, "");
+    test_result("d='2024/03/15'; d.day+=3; d.day", 18.0, "");
+    test_result("d='2024/03/15'-'2024/01/01'; d.days=1; d.days", 1.0, "days");
+    test_error("d='2024/03/15'; d.month=13;", ErrorId::InvDateValue);
+    test_error("d='2024/01/31'; d.month = 2;", ErrorId::InvDate);
+    test_error("d='2023/02/28'; d.day = 29;", ErrorId::InvDate);
+    test_result("d='2024/01/31'; d.day = 29; d.month = 2; d.day", 29.0, "");
+    test_error("d='2024/03/15'; d.day=1.5;", ErrorId::ValueError);
+    test_error("d=3; d.year=2022;", ErrorId::DateFragNoDate);
+    test_error("now().year=2022;", ErrorId::NotAssignable);
+    test_error("x=3; x.km=2;", ErrorId::NotAssignable);
+    test_error("l=1,2; l[0:1]=3;", ErrorId::NotAssignable);
+    test_error("3=4;", ErrorId::NotAssignable);
+    test_error("f(x)=1;", ErrorId::NotAssignable);
+    test_error("l=1,2; l[2]=3;", ErrorId::IndexOutOfRange);
+}
+
+#[test]
+fn test_previous_results(){
+    test_result("10+20+30; +40;", 100.0, "");
+    test_result("10+20+30; _*2;", 120.0, "");
+    test_result("3; *2+1;", 7.0, "");
+    test_result("3; +1*2;", 5.0, "");
+    test_result("3; -1;", -1.0, "");
+    test_bool("3; >2;", true);
+    test_result("x=3km; .=m;", 3000.0, "m");
+    test_result("A=10; +20; B=50; +=30; +=40; C=_;", 120.0, "");
+    test_result("x=1; #y=2; _;", 2.0, "");
+    test_result("1+1;\n5;\n$1+$2;", 7.0, "");
+    test_result("1+1; 4;\n5;\n$1;", 4.0, "");
+    test_result("#a=5;\n$1;", 5.0, "");
+    test_result("a=1;\n#b=a+1;\n{ c = $2*3; }\n$3;", 6.0, "");
+    test_error("*2;", ErrorId::NoResult);
+    test_error("1;\n$3;", ErrorId::NoResult);
+    test_error("3; +=2;", ErrorId::NoLastVar);
+}
+
+#[test]
+fn test_lambdas(){
+    test_result("double = x => x*2; double(3);", 6.0, "");
+    test_result("add = (a, b) => a+b; add(3, 4);", 7.0, "");
+    test_result("double = x => x*2; double(3)+1;", 7.0, "");
+    test_result("double = x => x*2; double(3)^2;", 36.0, "");
+    test_result("g = sqrt; g(16)+1;", 5.0, "");
+    test_result("function apply(f, x, y) { f(x, y); } apply((a, b) => a*b, 2, 3);", 6.0, "");
+    test_result("function apply(f, x) { (f(x)) + 1; } apply(x => x*2, 3);", 7.0, "");
+    test_result("function k() { secret(); } function secret() { 7; } k();", 7.0, "");
+    test_result("function outer() { 1; } { outer(); }", 1.0, "");
+    test_result("function f() { 1; } function g() { f(); } { function f() { 2; } g(); }", 1.0, "");
+    test_error("function h() { inner(); } { function inner() { 3; } h(); }", ErrorId::FuncHasErrors);
+    test_list("map((1,2,3), x => x*2)", &[2.0, 4.0, 6.0], "");
+    test_list("map((1,2,3), x => 2x)", &[2.0, 4.0, 6.0], "");
+    test_list("rate=10; map(1...3, x => x*rate)", &[10.0, 20.0, 30.0], "");
+    test_list("map((1,4,9), sqrt)", &[1.0, 2.0, 3.0], "");
+    test_list("function dbl(a) { a*2; } map((1,2), dbl)", &[2.0, 4.0], "");
+    test_list("map(((1,2),(3,4)), (a, b) => a*b)", &[2.0, 12.0], "");
+    test_list("filter(1...6, x => x%%2 == 0)", &[2.0, 4.0, 6.0], "");
+    test_result("count(1...6, x => x > 4)", 2.0, "");
+    test_result("reduce(1...4, (a, b) => a+b, 10)", 20.0, "");
+    test_result("reduce(1...4, (a, b) => a*b)", 24.0, "");
+    test_result("adder = x => y => x+y; add2 = adder(2); add2(3);", 5.0, "");
+    test_result("function mk(r) { x => x*r; } a = mk(2); b = mk(3); a(1);", 2.0, "");
+    test_result("function mk(r) { x => x*r; } a = mk(2); b = mk(3); b(1);", 3.0, "");
+    test_result("function mk(r) { y = r+1; x => x*y; } a = mk(2); b = mk(3); a(1) + b(1);", 7.0, "");
+    test_result("fact = n => n <= 1 ? 1 : n*fact(n-1); fact(5);", 120.0, "");
+    test_result("sin=3; sin*2", 6.0, "");
+    test_error("filter((1,2), x => x*2)", ErrorId::FuncArgWrongType);
+    test_error("map(3, x => x)", ErrorId::FuncArgWrongType);
+    test_error("map((1,2), 3)", ErrorId::FuncArgWrongType);
+    test_error("map((1,2), (a, b) => a+b)", ErrorId::FuncArgWrong);
+    test_error("f = x => x; f(1, 2);", ErrorId::FuncArgWrong);
+    let (_, errors) = get_results_with_includes("map((1,2), (a, b) => a+b)", &[]);
+    assert!(errors[0].message.contains("`(a, b) => a+b`")); //a lambda is named by its text.
+    test_error("reduce(filter((1,2), x => x > 5), (a, b) => a+b)", ErrorId::FuncArgWrongType);
+}
+
+#[test]
+fn test_default_and_variadic_params(){
+    test_result("function tax(amount, rate = 21) { amount*rate/100; } tax(200);", 42.0, "");
+    test_result("function tax(amount, rate = 21) { amount*rate/100; } tax(200, 6);", 12.0, "");
+    test_result("function f(a, b = a*2) { a+b; } f(3);", 9.0, "");
+    test_result("function total(items...) { sum(items); } total(1, 2, 3);", 6.0, "");
+    test_result("function total(items...) { count(items, x => x > 0); } total();", 0.0, "");
+    test_result("function f(a, b = 1, rest...) { a+b; } f(1);", 2.0, "");
+    test_result("function f(a, b = 1, rest...) { a+b+sum(rest); } f(1, 2, 3, 4);", 10.0, "");
+    test_error("function tax(amount, rate = 21) { amount*rate/100; } tax();", ErrorId::FuncArgWrong);
+    test_error("function tax(amount, rate = 21) { amount*rate/100; } tax(1, 2, 3);", ErrorId::FuncArgWrong);
+    test_error("function f(a = 1, b) { a; }", ErrorId::FuncParamDef);
+    test_error("function f(a..., b) { a; }", ErrorId::FuncParamDef);
+}
+
+#[test]
+fn test_overloads(){
+    test_result("function f(a) { a*10; } function f(a, b) { a+b; } f(3);", 30.0, "");
+    test_result("function f(a) { a*10; } function f(a, b) { a+b; } f(3, 4);", 7.0, "");
+    test_result("function f(a) { a*10; } function f(a, b, rest...) { 100; } f(1, 2, 3, 4);", 100.0, "");
+    test_result("function f(a, b) { a*b; } function f(a, b, c) { a*b*c; } f((2, 3));", 6.0, "");
+    test_list("function f(a) { a*10; } function f(a, b) { a+b; } map((1, 2), f);", &[10.0, 20.0], "");
+    test_result("function f(a) { a*10; } function g(x) { function f(a, b) { a+b; } f(x) + f(x, 1); } g(3);", 34.0, "");
+    test_no_error("function f(a) { a*10; } function f(a, b) { a+b; }");
+    test_error("function f(a) { a*10; } function f(a, b) { a+b; } f();", ErrorId::FuncNoOverload);
+    test_error("function f(a) { a*10; } function f(b) { b; }", ErrorId::WFunctionOverride);
+    test_error("function sin(a, b) { a+b; }", ErrorId::WFunctionOverride);
+}
+
+#[test]
+fn test_recursion(){
+    test_result("function fact(n) { n <= 1 ? 1 : n*fact(n-1); } fact(5);", 120.0, "");
+    test_result("function fib(n) { n < 2 ? n : fib(n-1) + fib(n-2); } fib(10);", 55.0, "");
+    test_result("function f(n) { m = n*2; n <= 0 ? 0 : f(n-1) + m; } f(3);", 12.0, "");
+    test_result("function f(a) { a*2; } function g(x) { f(x)+1; } g(3);", 7.0, "");
+    test_error("function inf(n) { inf(n+1); } inf(1);", ErrorId::RecursionTooDeep);
+    test_result("#define recursion=5\nfunction fact(n) { n <= 1 ? 1 : n*fact(n-1); } fact(5);", 120.0, "");
+    test_error("#define recursion=5\nfunction fact(n) { n <= 1 ? 1 : n*fact(n-1); } fact(6);", ErrorId::RecursionTooDeep);
+    test_error("#define recursion=5\nfunction f(n) { n <= 0 ? 0 : g(n-1); } function g(n) { n <= 0 ? 0 : f(n-1); } f(10);", ErrorId::RecursionTooDeep);
+    test_result("function f(n) { n <= 0 ? 0 : g(n-1); } function g(n) { n <= 0 ? 0 : f(n-1); } f(50);", 0.0, "");
+    test_error("#define recursion=100000\nfunction f(n) { n <= 0 ? 0 : f(n-1) + 1; } f(600);", ErrorId::WRecursionLimit);
+    test_error("#define recursion=100000\nfunction f(n) { n <= 0 ? 0 : f(n-1) + 1; } f(600);", ErrorId::RecursionTooDeep);
+    test_result("function f(a) { a*2; } { function f(a) { a*3; } f(1); }; f(1);", 2.0, "");
+}
+
+#[test]
+fn test_selectors(){
+    test_result("item1=10; item2=20; item10=5; item=1; itemX=3; sum(item#);", 35.0, "");
+    test_result("item1=10; item2=20; item10=5; item=1; itemX=3; sum(item*);", 39.0, "");
+    test_list("item1=10; item2=20; item10=5; item=1; itemX=3; item2..item10;", &[20.0, 5.0], "");
+    test_list("item2=20; item1=10; item#;", &[20.0, 10.0], "");
+    test_list("a=1; b=2; c=3; d=4; b..c;", &[2.0, 3.0], "");
+    test_result("a=2; b=3; a*b;", 6.0, "");
+    test_list("a1=1; a2=2; map(a#, x => x*10);", &[10.0, 20.0], "");
+    test_error("a1=1; a# = 3;", ErrorId::NotAssignable);
+}
+
+#[test]
+fn test_text_values(){
+    test_text("\"Total: \" + str(5km);", "Total: 5km");
+    test_text("s = \"ab\"; s + \"cd\";", "abcd");
+    test_text("str(1/3);", "0.33333");
+    test_text("#define precision=2\nstr(1/3);", "0.33");
+    test_text("str((1, 2));", "1, 2");
+    test_text("str(1 == 1);", "true");
+    test_text("format(255, \"hex\");", "0xFF");
+    test_text("format(5, \"bin\");", "0b101");
+    test_text("format(1/3, \"0.00\");", "0.33");
+    test_text("format(2.6kg, \"0\");", "3kg");
+    test_text("\"12.5\";", "12.5");
+    test_bool("\"a\" < \"b\";", true);
+    test_bool("\"a\" == \"a\";", true);
+    test_error("\"a\" + 1;", ErrorId::NoOp);
+    test_error("format(1, \"x\");", ErrorId::FuncArgWrongType);
+    test_error("format(\"a\", \"hex\");", ErrorId::FuncArgWrongType);
+}
+
+#[test]
+fn test_echo_templates(){
+    test_echo("!//plain comment", "plain comment");
+    test_echo("a=100; b=23.4;\n!//the result of !`a+b` = $", "the result of a+b = 123.4");
+    test_echo("a=100; b=23.4;\n!//`a+b` minus `.4` is `$0 - $1`.", "123.4 minus 0.4 is 123.");
+    test_echo("x=5km;\n!//x is $", "x is 5km");
+    test_echo("!//`(1,2)`", "1, 2");
+    test_error("!//`1+2", ErrorId::Expected);
+    test_error("!//`1+2` $1", ErrorId::NoResult);
+    test_error("!//`1+2)`", ErrorId::Expected);
+    test_echo("!//costs $$5 each", "costs $5 each");
+    let (_, errors) = get_results_with_includes("!//`1+2", &[]);
+    assert_eq!(errors.len(), 1); //a parse error is reported once.
+}
+
+#[test]
+fn test_echo_modes(){
+    test_echoed_code("!b=60/3; //comment", "b=60/3");
+    test_echoed_code("x=1;\n!!q=100/10; //some comment", "q=100/10; //some comment");
+    test_echoed_code("!!a=1; #b=2;", "a=1;");
+    test_echoed_code("!///\na=1;\nb=a*2; //double", "b=a*2; //double");
+    test_result("!5!;", 120.0, "");
+    test_result("!///\na=1;\n///!\nb=2;\n!//done\nb;", 2.0, "");
+    let (results, _) = get_results_with_includes("!///\nif (1 > 0) { a=2; }", &[]);
+    assert!(results.iter().any(|value| value.echo.as_ref().is_some_and(|echo| echo.end - echo.start == "a=2;".len()))); //the statements in the block are echoed too.
+    let (results, _) = get_results_with_includes("!///\na=1;\n///!", &[]);
+    assert_eq!(results.len(), 1); //no empty statement after the end marker.
+}
+
+#[test]
+fn test_approx(){
+    test_bool("0.1 + 0.2 ~= 0.3;", true);
+    test_bool("PI ~= 3.14;", false);
+    test_bool("#define precision=2\nPI ~= 3.14;", true);
+    test_bool("PI ~= 3.14 within 0.01;", true);
+    test_bool("1km ~= 1001m within 1;", true);
+    test_bool("1km ~= 1002m within 1m;", false);
+    test_bool("1km ~= 1000.5m within 1mm;", false);
+    test_bool("20C ~= 20.5C within 1C;", true);
+    test_error("1 ~= \"a\";", ErrorId::NoOp);
+    test_error("1 ~= 2 within \"a\";", ErrorId::ExpectedNumericValue);
+    test_error("1 ~= 2 within -1;", ErrorId::ValueError);
+    test_error("1m ~= 1.5m within 1kg;", ErrorId::UnitPropDiff);
+}
+
+#[test]
+fn test_asserts(){
+    test_no_error("x=3;assert(x > 2);assert_eq(x, 3);assert_eq(1m, 100cm);assert_eq((1,2), (1,2));assert_approx(PI, 3.14, 0.01);");
+    test_result("x=3;assert(x > 2);", 3.0, ""); //a passing assertion is muted.
+    test_bool("!assert(1 == 1);", true); //...unless echoed.
+    test_error("assert(1 > 2);", ErrorId::AssertFailed);
+    test_error("assert_eq(1, 2);", ErrorId::AssertEqFailed);
+    test_error("assert_eq((1,2), (1,3));", ErrorId::AssertEqFailed);
+    test_error("assert_eq(1m, 1kg);", ErrorId::AssertEqFailed);
+    test_error("assert_eq(1, \"1\");", ErrorId::AssertEqFailed);
+    test_error("assert_approx(PI, 3, 0.1);", ErrorId::AssertApproxFailed);
+    test_error("assert_approx(1km, 1010m, 2m);", ErrorId::AssertApproxFailed);
+    test_error("assert(1);", ErrorId::FuncArgWrongType);
+}
+
+#[test]
+fn test_includes(){
+    let lib = ("lib", "function sqr(x) { x*x }; half = 0.5;");
+    test_include_result(&[lib], "#include \"lib\"\nsqr(3);", 9.0, "");
+    test_include_result(&[lib], "#include \"lib\"\nhalf*4;", 2.0, "");
+    test_include_result(&[lib, ("lib2", "#include \"lib\"\nten = sqr(2)+6;")], "#include \"lib2\"\nten+half;", 10.5, "");
+    test_include_result(&[lib], "{ #include \"lib\"\n sqr(4) }", 16.0, "");
+    test_include_error(&[], "#include \"nothing\";", ErrorId::SourceNotFound, "src1");
+    test_include_error(&[("a", "#include \"b\""), ("b", "#include \"a\"")], "#include \"a\";", ErrorId::IncludeCycle, "b");
+    test_include_error(&[("a", "#include \"src1\"")], "#include \"a\";", ErrorId::IncludeCycle, "a");
+    test_include_error(&[("a", "1+;\nx")], "#include \"a\";", ErrorId::VarNotDef, "a");
+    test_error("#include lib;", ErrorId::Expected);
+    let (results, _) = get_results_with_includes("#include \"lib\"\na+b;", &[("lib", "a=1; b=2;")]);
+    assert_eq!(results.iter().filter_map(|value| value.as_number()).map(|number| number.to_double()).collect::<Vec<f64>>(), vec![3.0]); //the library is muted.
+}
+
+#[test]
+fn test_records(){
+    test_result("p = {x: 3m, y: 4m}; p.x;", 3.0, "m");
+    test_result("p = {x: 3m, y: 4m}; p.y.cm;", 400.0, "cm");
+    test_result("p = {m: 3, y: 4m}; p.m;", 3.0, ""); //a field before a unit.
+    test_result("line = {from: {x: 1, y: 2}, to: {x: 5, y: 7}}; line.to.y - line.from.y;", 5.0, "");
+    test_result("function f(x) { {sq: x*x, half: x/2} }; f(6).half;", 3.0, "");
+    test_text("str({x: 1m, y: \"a\"});", "{x: 1m, y: a}");
+    test_no_error("assert_eq({x: 1, y: 2}, {x: 1, y: 2});");
+    test_no_error("assert_eq({x: 1, y: 2}, {y: 2, x: 1});");
+    test_error("assert_eq({x: 1, y: 2}, {x: 1, z: 2});", ErrorId::AssertEqFailed);
+    test_result("p = {x: 3m}; p.x = 5m; p.x;", 5.0, "m");
+    test_result("p = {x: 1, y: {z: 2}}; p.y.z += 3; p.y.z;", 5.0, "");
+    test_result("p = {x: 1}; p.y = 2; p.x + p.y;", 3.0, "");
+    test_error("p = {x: 3m}; p.z;", ErrorId::FieldNotDef);
+    test_error("p = {x: 3m, x: 4m};", ErrorId::FieldRedef);
+    test_error("p = {x: 3m, y 4m};", ErrorId::Expected);
+}
+
+#[test]
+fn test_vectors(){
+    test_list("(1,2,3)*2;", &[2.0, 4.0, 6.0], "");
+    test_list("2-(1,2);", &[1.0, 0.0], "");
+    test_list("(1,2,3)+(4,5,6);", &[5.0, 7.0, 9.0], "");
+    test_list("(1m,2m)+(1cm, 1km);", &[1.01, 1002.0], "m");
+    test_list("a=(1,2); a^2;", &[1.0, 4.0], "");
+    test_error("(1,2)+(1,2,3);", ErrorId::ListSizeDiff);
+    test_error("(1,2)*\"a\";", ErrorId::NoOp);
+    test_error("(1,\"a\")*2;", ErrorId::NoOp);
+
+    test_result("dot((1,2,3),(4,5,6));", 32.0, "");
+    test_result("dot((1m,2m),(3,4));", 11.0, "m");
+    test_list("cross((1,0,0),(0,1,0));", &[0.0, 0.0, 1.0], "");
+    test_result("norm((3,4));", 5.0, "");
+    test_result("norm((3m,400cm));", 5.0, "m");
+    test_list("normalize((3,4));", &[0.6, 0.8], "");
+    test_error("dot((1,2),(1,2,3));", ErrorId::ListSizeDiff);
+    test_error("cross((1,2),(1,2));", ErrorId::FuncArgWrongType);
+    test_error("norm(5);", ErrorId::FuncArgWrongType);
+    test_error("normalize((0,0));", ErrorId::FuncArgWrongType);
+    test_error("norm((1foo,2foo));", ErrorId::UnitNotDef);
+    test_error("dot((1xyz,2),(1,1));", ErrorId::UnitNotDef);
+}
+
+#[test]
+fn test_matrices(){
+    test_text("str([1,2;3,4]);", "[1, 2; 3, 4]");
+    test_text("str([1,2;3,4]*[5,6;7,8]);", "[19, 22; 43, 50]");
+    test_text("str([1,2;3,4]+[1,1;1,1]);", "[2, 3; 4, 5]");
+    test_text("str(2*[1,2;3,4]/4);", "[0.5, 1; 1.5, 2]");
+    test_text("str(transpose([1,2,3;4,5,6]));", "[1, 4; 2, 5; 3, 6]");
+    test_text("str(inverse([4,7;2,6]));", "[0.6, -0.7; -0.2, 0.4]");
+    test_text("str([1km, 2m]);", "[1000m, 2m]"); //SI units.
+    test_text("str([1,2;3,4]*2m);", "[2m, 4m; 6m, 8m]");
+    test_text("str([2m,4m]/2m);", "[1, 2]");
+    test_list("[1m,2m;3m,4m]*(1,1);", &[3.0, 7.0], "m");
+    test_error("[1m, 1kg];", ErrorId::UnitPropDiff);
+    test_error("[1m,2m]+[1kg,1kg];", ErrorId::UnitPropDiff);
+    test_error("[1m,2;3,4];", ErrorId::WAssumingUnit);
+    test_list("[1,2;3,4]*(1,1);", &[3.0, 7.0], "");
+    test_result("det([1,2;3,4]);", -2.0, "");
+    test_result("det([2,0,0;0,3,0;0,0,4]);", 24.0, "");
+    test_list("solve([2,1,-1;-3,-1,2;-2,1,2], (8,-11,-3));", &[2.0, 3.0, -1.0], "");
+    test_error("[1,2;3];", ErrorId::InvMatrix);
+    test_error("[1,\"a\"];", ErrorId::InvMatrix);
+    test_error("[1,2]*[1,2];", ErrorId::MatrixSizeDiff);
+    test_error("[1,2;3,4]+[1,2];", ErrorId::MatrixSizeDiff);
+    test_error("[1,2;3,4]*(1,1,1);", ErrorId::MatrixSizeDiff);
+    test_error("det([1,2,3]);", ErrorId::MatrixNotSquare);
+    test_error("inverse([1,2;2,4]);", ErrorId::MatrixSingular);
+    test_error("solve([1,2;2,4], (1,2));", ErrorId::MatrixSingular);
+    test_error("solve([1,2;3,4], (1,2,3));", ErrorId::MatrixSizeDiff);
+}
+
+#[test]
+fn test_solve(){
+    test_result("solve(x^2 == 3, x, 1);", 1.7320508, "");
+    test_result("solve(x^2 - 3, x, -1);", -1.7320508, "");
+    test_result("solve(cos(x) == x, x, (0, 1));", 0.7390851, "");
+    test_result("a = 3; solve(a*x == 12, x, 0);", 4.0, "");
+    test_result("solve(x + 2m == 5m, x, 0);", 3.0, "m");
+    test_result("solve(2*x == 10m, x, 1);", 5.0, "m");
+    test_result("solve(x - 1.5ft, x, 1m);", 0.4572, "m");
+    test_result("solve(sqrt(x) == 3, x, 0);", 9.0, "");
+    test_result("function q(y) { y^3 - 8; } solve(q(x), x, 1);", 2.0, "");
+    test_result("x = 5; solve(x^2 - 2, x, 1); x;", 5.0, "");
+    test_list("solve([1,2;3,4], (1,2));", &[0.0, 0.5], "");
+    test_error("solve(x^2 + 1, x, 0);", ErrorId::SolveNoRoot);
+    test_error("solve(x^2 - 4, x, (3, 5));", ErrorId::SolveNoRoot);
+    test_error("solve(1/x, x, (-1, 1));", ErrorId::SolveNoConvergence);
+    test_error("solve(x - 2, x, \"a\");", ErrorId::FuncArgWrongType);
+    test_error("solve(x + y, x, 1);", ErrorId::VarNotDef);
+}
+
+#[test]
+fn test_symbolic(){
+    test_text("str(derive(x^3, x));", "3*x^2");
+    test_text("str(derive(3*x^2 + 2*x + 1, x));", "6*x + 2");
+    test_text("str(derive(sin(x)*cos(x), x));", "cos(x)^2 - sin(x)^2");
+    test_text("str(derive(1/x, x));", "-1/x^2");
+    test_text("str(derive(sqrt(x), x));", "1/(2*sqrt(x))");
+    test_text("str(derive(e^(2*x), x));", "2*e^(2*x)");
+    test_text("str(derive(a*x^2, x));", "2*a*x");
+    test_text("str(derive(x/(x + 1), x));", "1/(x + 1)^2");
+    test_text("f = derive(x^4, x); str(derive(f, x));", "12*x^2");
+    test_text("str(simplify(x*1 + 0*y + x));", "2*x");
+    test_text("str(simplify(2*(3*x)));", "6*x");
+    test_text("str(simplify(x*x*x));", "x^3");
+    test_text("str(simplify(x - -(y + 1)));", "x + y + 1");
+    test_text("str(simplify(-(x + 1)));", "-(x + 1)");
+    test_error("derive(2^x, x);", ErrorId::NotDerivable);
+    test_error("derive(round(x), x);", ErrorId::NotDerivable);
+    test_error("derive(x > 1, x);", ErrorId::NoSymbolicExpr);
+    test_error("derive(x, 1);", ErrorId::FuncArgWrongType);
+    test_error("derive(x);", ErrorId::FuncArgWrong);
+    test_error("derive(x^2, x) + 1;", ErrorId::NoOp);
+    test_error("derive(foo(x), x);", ErrorId::FuncNotDef);
+    test_text("str(simplify((x^2)^3));", "x^6");
+    test_text("str(simplify((x^2)^0.5));", "(x^2)^0.5");
+}
+
+#[test]
+fn test_integrate(){
+    test_result("integrate(x => x^2, 0, 3);", 9.0, "");
+    test_result("integrate(x => sin(x), 0, PI);", 2.0, "");
+    test_result("function power(t) { t < 2 ? 100 : 50; } integrate(power, 0, 10);", 600.0, "");
+    test_result("integrate(x => 1, 0m, 2km);", 2000.0, "m");
+    test_result("integrate(x => 1km, 0, 2);", 2.0, "km");
+    test_result("diff(x => x^3, 2);", 12.0, "");
+    test_result("diff(x => sin(x), 0);", 1.0, "");
+    test_result("function v(t) { 3m*t; } diff(v, 10);", 3.0, "m");
+    test_error("integrate(5, 0, 1);", ErrorId::FuncArgWrongType);
+    test_error("integrate(x => 1/x, 0, 1);", ErrorId::NotFinite);
+    test_error("integrate(x => (x-x)/(x-x), 0, 1);", ErrorId::NotFinite);
+    test_result("integrate(x => x < 0.5 ? 1km : 500m, 0, 1);", 0.75, "km");
+    test_error("integrate(x => x < 0.5 ? 1km : 500kg, 0, 1);", ErrorId::UnitPropDiff);
+    test_error("diff(x => (x, x), 1);", ErrorId::FuncArgWrongType);
+}
+
+#[test]
+fn test_complex(){
+    test_text("str(3+4i);", "3 + 4i");
+    test_text("str((3+4i)*(1-2i));", "11 - 2i");
+    test_text("str((3-4i)/(1+1i));", "-0.5 - 3.5i");
+    test_text("str(-(3+4i) + 1);", "-2 - 4i");
+    test_text("str(2*(1+1i)^2);", "4i");
+    test_text("str(sqrt(-4));", "2i");
+    test_text("str(sqrt(3+4i));", "2 + 1i");
+    test_text("str(conj(3+4i));", "3 - 4i");
+    test_text("str((3+4i).polar);", "5∠53.1301°");
+    test_text("z = (1+1i).polar; str(z.rect);", "1 + 1i");
+    test_text("#define electric\n str(2+3j);", "2 + 3j");
+    test_text("#define electric\n str(sqrt(-9));", "3j");
+    test_text("str((1i, 2) * 1i);", "-1, 2i");
+    test_result("(1+2i)*(1-2i);", 5.0, "");
+    test_result("1i^2;", -1.0, "");
+    test_result("i = 5; 2i;", 10.0, "");
+    test_result("re(3+4i);", 3.0, "");
+    test_result("im(3+4i);", 4.0, "");
+    test_result("abs(3+4i);", 5.0, "");
+    test_result("arg(-1-1i);", -2.3561945, "rad");
+    test_bool("(1+2i) == (1+2i);", true);
+    test_error("re(\"a\");", ErrorId::FuncArgWrongType);
+    test_error("\"a\".polar;", ErrorId::InvFormat);
+    test_error("(1+2i) < (1+3i);", ErrorId::NoOp);
+    test_error("1km + 2i;", ErrorId::WComplexUnit);
+    test_error("(-8)^(1/3);", ErrorId::ValueError);
+}
@@ -0,0 +1,133 @@
+use std::f64::consts::PI;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+const MAX_INT_POWER: f64 = 1024.0; //larger integer powers are calculated in polar form.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ComplexFormat {
+    Rect, //`3 + 4i`
+    Polar, //`5∠53.1301°`
+}
+
+///A complex number, as `re + im*i`. The symbol of the imaginary unit is `j` in electric mode.
+#[derive(Clone)]
+pub struct Complex {
+    pub re: f64,
+    pub im: f64,
+    pub fmt: ComplexFormat,
+    pub symbol: char,
+}
+
+impl Complex {
+    pub fn new(re: f64, im: f64, symbol: char) -> Self {
+        Complex { re, im, fmt: ComplexFormat::Rect, symbol }
+    }
+
+    pub fn from_polar(abs: f64, arg: f64, symbol: char) -> Self {
+        Complex::new(abs * arg.cos(), abs * arg.sin(), symbol)
+    }
+
+    ///With the same format and symbol as self.
+    fn with(&self, re: f64, im: f64) -> Complex {
+        Complex { re, im, ..self.clone() }
+    }
+
+    pub fn is_real(&self) -> bool {
+        self.im == 0.0
+    }
+
+    pub fn abs(&self) -> f64 {
+        self.re.hypot(self.im)
+    }
+
+    ///In radians, between -PI and PI.
+    pub fn arg(&self) -> f64 {
+        self.im.atan2(self.re)
+    }
+
+    pub fn conj(&self) -> Complex {
+        self.with(self.re, -self.im)
+    }
+
+    ///The principal root, with re >= 0.
+    pub fn sqrt(&self) -> Complex {
+        let abs = self.abs();
+        let re = ((abs + self.re) / 2.0).sqrt();
+        let im = ((abs - self.re) / 2.0).sqrt();
+        self.with(re, if self.im < 0.0 { -im } else { im })
+    }
+
+    ///An integer power is multiplied out, so that `i^2` is exactly -1.
+    pub fn pow(&self, exponent: &Complex) -> Complex {
+        if exponent.is_real() && exponent.re.fract() == 0.0 && exponent.re.abs() <= MAX_INT_POWER {
+            return self.powi(exponent.re as i32);
+        }
+        if self.re == 0.0 && self.im == 0.0 {
+            return self.with(if exponent.re > 0.0 { 0.0 } else { f64::NAN }, 0.0);
+        }
+        //z^w = e^(w*ln(z))
+        let ln = self.with(self.abs().ln(), self.arg());
+        let exponent = exponent * &ln;
+        let power = Complex::from_polar(exponent.re.exp(), exponent.im, self.symbol);
+        self.with(power.re, power.im)
+    }
+
+    fn powi(&self, exponent: i32) -> Complex {
+        let mut power = self.with(1.0, 0.0);
+        let mut factor = self.clone();
+        let mut n = exponent.unsigned_abs();
+        while n > 0 {
+            if n % 2 == 1 {
+                power = &power * &factor;
+            }
+            factor = &factor * &factor;
+            n /= 2;
+        }
+        if exponent < 0 { &self.with(1.0, 0.0) / &power } else { power }
+    }
+
+    pub fn arg_in_degrees(&self) -> f64 {
+        self.arg() * 180.0 / PI
+    }
+}
+
+impl Add for &Complex {
+    type Output = Complex;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        self.with(self.re + rhs.re, self.im + rhs.im)
+    }
+}
+
+impl Sub for &Complex {
+    type Output = Complex;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        self.with(self.re - rhs.re, self.im - rhs.im)
+    }
+}
+
+impl Mul for &Complex {
+    type Output = Complex;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        self.with(self.re * rhs.re - self.im * rhs.im, self.re * rhs.im + self.im * rhs.re)
+    }
+}
+
+impl Div for &Complex {
+    type Output = Complex;
+
+    fn div(self, rhs: Self) -> Self::Output {
+        let denominator = rhs.re * rhs.re + rhs.im * rhs.im;
+        self.with((self.re * rhs.re + self.im * rhs.im) / denominator, (self.im * rhs.re - self.re * rhs.im) / denominator)
+    }
+}
+
+impl Neg for &Complex {
+    type Output = Complex;
+
+    fn neg(self) -> Self::Output {
+        self.with(-self.re, -self.im)
+    }
+}
@@ -1,3 +1,4 @@
+use std::cmp::Ordering;
 use std::ops;
 use crate::errors::Error;
 
@@ -121,14 +122,25 @@ impl Timepoint {
     pub fn is_valid(&self) -> bool {
         if self.year.is_none() { return false; }
         if self.day.is_none() { return false; }
+        if self.month == Month::NONE { return false; }
         if let Day::Value(day) = self.day {
-            if day <= 0 || day > 31 { return false; }
+            if day <= 0 || day > self.days_in_month() { return false; }
         }
-        if self.month == Month::NONE { return false; }
 
         true
     }
 
+    pub fn to_formatted_string(&self) -> String {
+        let norm_day = self.get_normalized_day();
+        let str_day_formatted = if norm_day == 0 {
+            "??".to_string()
+        } else {
+            norm_day.to_string()
+        };
+        let str_year = if let Some(year) = self.year { year.to_string()} else { "????".to_string()};
+        format!("{0}-{1:?}-{2}", &str_year, &self.month, &str_day_formatted)
+    }
+
     ///Always returns a number, but 0 if undetermined.
     pub fn get_normalized_day(&self) -> i8 {
         //check leap year: if y/4 and not y/100 : leap year. Also, if y/100 and y/400: leap year.
@@ -181,6 +193,12 @@ impl Timepoint {
         }
     }
 
+    ///Chronological order. Missing years or days are compared as 0.
+    pub fn compare(&self, other: &Timepoint) -> Ordering {
+        (self.year.unwrap_or(0), self.month as i32, self.get_normalized_day())
+            .cmp(&(other.year.unwrap_or(0), other.month as i32, other.get_normalized_day()))
+    }
+
     fn days_in_month(&self) -> i8 {
         match self.month {
             Month::JAN => 31,
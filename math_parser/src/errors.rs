@@ -26,9 +26,13 @@ define_errors!(
     FuncNoOpenPar: E : "Missing `(` for function `{function}`.",
     FuncFailed: E : "Error in function `{function}`.",
     FuncHasErrors: E : "Function `{function}` contains errors.",
+    FuncNoOverload: E : "No overload of function `{function}` takes this number of arguments. Possible number of arguments: {arities}.",
+    RecursionTooDeep: E : "Function `{function}` exceeds the maximum recursion depth of {max_depth}.",
+    FuncParamDef: E : "Invalid definition of parameter `{parameter}`. {param_info}",
 
     WDivImplMult: W : "Warning: ambiguous expression: division followed by implicit multiplication.",
     WPowImplMult: W : "Warning: ambiguous expression: exponential combined with implicit multiplication.",
+    WRecursionLimit: W : "Warning: the recursion depth is limited to {max_depth}.",
     WAmbiguousComma: W : "Ambiguous comma. Could be a list item separator or a decimal point.",
     WAssumingUnit: W : "Warning: not all values have a unit. Assuming same unit as the other values.",
     WVarIsUnit: W : "Warning; variable `{variable}` overrides unit with same name.",
@@ -44,6 +48,7 @@ define_errors!(
     UnitPropWrong: E : "The units are not for the property {unit_property}.",
     ConstRedef: E : "Redefinition of constant `{constant}` not allowed.",
     WConstRedef: W : "Warning: redefinition of constant `{constant}`.",
+    WComplexUnit: W : "Warning: a complex number has no unit. The unit `{unit}` is dropped.",
     DateFragNoDate: E : "Cannot get `{fragment}` fragment. Value is not a date.",
     DateFragNoDuration: E : "Cannot get `{fragment}` fragment. Value is not a duration.",
 
@@ -59,9 +64,35 @@ define_errors!(
     InvDateStrForFormat: E : "Invalid date string for format `{format}`",
     InvDateValue: E : "Invalid date value `{date_value}` for {date}.",
     InvDate: E : "Invalid date.",
+    ListSizeDiff: E : "The lists have a different number of elements: {size1} and {size2}.",
+    InvMatrix: E : "Invalid matrix: {matrix_info}",
+    MatrixSizeDiff: E : "The matrix sizes don't match: {size1} and {size2}.",
+    MatrixNotSquare: E : "The matrix is not square: {size}.",
+    MatrixSingular: E : "The matrix is singular.",
+    SolveNoRoot: E : "No root found for `{variable}` between {lower} and {upper}.",
+    SolveNoConvergence: E : "Solving for `{variable}` did not converge.",
+    NoSymbolicExpr: E : "Not supported in a symbolic expression: `{expression}`.",
+    NotDerivable: E : "Cannot derive `{expression}`.",
+    NotFinite: E : "The result of `{function}` is not a finite number.",
     InvList: E : "Cannot convert value list. {extra_info}",
     InvNumberStr: E : "Invalid number string: `{number_string}`",
     InvFormat: E : "Invalid format for this type of value: `{value}`",
+    InvIndex: E : "Invalid index: {index_info}",
+    IndexOutOfRange: E : "Index {index} is out of range for a list with {size} element(s).",
+    InvRange: E : "Invalid range: {range_info}",
+    NotAssignable: E : "Cannot assign a value to `{expression}`.",
+    NoResult: E : "No result found for `{reference}`.",
+    NoLastVar: E : "The previous statement has no variable to assign to.",
+    FieldNotDef: E : "The record has no field `{field}`.",
+    FieldRedef: E : "Field `{field}` is defined more than once.",
+    CondNotBool: E : "Condition should be a boolean value, but found a `{value_type}`.",
+
+    SourceNotFound: E : "Source `{source}` not found.",
+    IncludeCycle: E : "Source `{source}` is already being included.",
+
+    AssertFailed: E : "Assertion `{assertion}` failed.",
+    AssertEqFailed: E : "Assertion failed: `{value1}` is not equal to `{value2}`.",
+    AssertApproxFailed: E : "Assertion failed: `{value1}` is not within {tolerance} of `{value2}`.",
 );
 
 #[derive(Clone)]
@@ -0,0 +1,205 @@
+use std::fmt::{Display, Formatter};
+use Expression::*;
+
+const MAX_SIMPLIFY_PASSES: usize = 50;
+
+///An unevaluated expression, for symbolic calculations like `derive()` and `simplify()`.
+#[derive(Clone, PartialEq)]
+pub enum Expression {
+    Num(f64),
+    Var(String),
+    Neg(Box<Expression>),
+    Add(Box<Expression>, Box<Expression>),
+    Sub(Box<Expression>, Box<Expression>),
+    Mul(Box<Expression>, Box<Expression>),
+    Div(Box<Expression>, Box<Expression>),
+    Pow(Box<Expression>, Box<Expression>),
+    Call(String, Box<Expression>), //a function with a single argument.
+}
+
+fn neg(e: Expression) -> Expression { Neg(Box::new(e)) }
+fn add(e1: Expression, e2: Expression) -> Expression { Add(Box::new(e1), Box::new(e2)) }
+fn sub(e1: Expression, e2: Expression) -> Expression { Sub(Box::new(e1), Box::new(e2)) }
+fn mul(e1: Expression, e2: Expression) -> Expression { Mul(Box::new(e1), Box::new(e2)) }
+fn div(e1: Expression, e2: Expression) -> Expression { Div(Box::new(e1), Box::new(e2)) }
+fn pow(e1: Expression, e2: Expression) -> Expression { Pow(Box::new(e1), Box::new(e2)) }
+fn call(name: &str, e: Expression) -> Expression { Call(name.to_string(), Box::new(e)) }
+
+impl Expression {
+    pub fn contains(&self, var: &str) -> bool {
+        match self {
+            Num(_) => false,
+            Var(name) => name == var,
+            Neg(e) | Call(_, e) => e.contains(var),
+            Add(e1, e2) | Sub(e1, e2) | Mul(e1, e2) | Div(e1, e2) | Pow(e1, e2) => e1.contains(var) || e2.contains(var),
+        }
+    }
+
+    ///The derivative to `var`, not simplified. Err contains the part that can't be derived.
+    pub fn derive(&self, var: &str) -> Result<Expression, Expression> {
+        Ok(match self {
+            Num(_) => Num(0.0),
+            Var(name) => Num(if name == var { 1.0 } else { 0.0 }),
+            Neg(e) => neg(e.derive(var)?),
+            Add(e1, e2) => add(e1.derive(var)?, e2.derive(var)?),
+            Sub(e1, e2) => sub(e1.derive(var)?, e2.derive(var)?),
+            Mul(e1, e2) => add(mul(e1.derive(var)?, *e2.clone()), mul(*e1.clone(), e2.derive(var)?)),
+            Div(e1, e2) => div(
+                sub(mul(e1.derive(var)?, *e2.clone()), mul(*e1.clone(), e2.derive(var)?)),
+                pow(*e2.clone(), Num(2.0))),
+            Pow(base, exponent) if !exponent.contains(var) => mul(
+                mul(*exponent.clone(), pow(*base.clone(), sub(*exponent.clone(), Num(1.0)))),
+                base.derive(var)?),
+            Pow(base, exponent) if **base == Var("e".to_string()) && var != "e" => mul(self.clone(), exponent.derive(var)?),
+            Pow(..) => return Err(self.clone()), //would need a logarithm.
+            Call(name, e) => {
+                let outer = match name.as_str() {
+                    "sin" => call("cos", *e.clone()),
+                    "cos" => neg(call("sin", *e.clone())),
+                    "tan" => div(Num(1.0), pow(call("cos", *e.clone()), Num(2.0))),
+                    "sqrt" => div(Num(1.0), mul(Num(2.0), self.clone())),
+                    "asin" => div(Num(1.0), call("sqrt", sub(Num(1.0), pow(*e.clone(), Num(2.0))))),
+                    "acos" => neg(div(Num(1.0), call("sqrt", sub(Num(1.0), pow(*e.clone(), Num(2.0)))))),
+                    "atan" => div(Num(1.0), add(Num(1.0), pow(*e.clone(), Num(2.0)))),
+                    "abs" => div(*e.clone(), self.clone()),
+                    _ => return Err(self.clone()),
+                };
+                mul(outer, e.derive(var)?)
+            },
+        })
+    }
+
+    ///Applies the simplification rules until nothing changes anymore.
+    pub fn simplify(&self) -> Expression {
+        let mut expression = self.clone();
+        for _ in 0..MAX_SIMPLIFY_PASSES {
+            let simplified = expression.simplify_once();
+            if simplified == expression {
+                break;
+            }
+            expression = simplified;
+        }
+        expression
+    }
+
+    //A division or power of constants is folded only if the result is exact, so that `1/3` and `2^0.5` stay as they are.
+    fn simplify_once(&self) -> Expression {
+        match self {
+            Num(_) | Var(_) => self.clone(),
+            Neg(e) => match e.simplify_once() {
+                Num(n) => Num(-n),
+                Neg(e) => *e,
+                e => neg(e),
+            },
+            Add(e1, e2) => match (e1.simplify_once(), e2.simplify_once()) {
+                (Num(n1), Num(n2)) => Num(n1 + n2),
+                (Num(0.0), e) | (e, Num(0.0)) => e,
+                (e1, Neg(e2)) => sub(e1, *e2),
+                (Neg(e1), e2) => sub(e2, *e1),
+                (e, Num(n)) if n < 0.0 => sub(e, Num(-n)),
+                (Num(n), e) => add(e, Num(n)), //constants last: `x + 1`
+                (e1, e2) if e1 == e2 => mul(Num(2.0), e1),
+                (e1, e2) => add(e1, e2),
+            },
+            Sub(e1, e2) => match (e1.simplify_once(), e2.simplify_once()) {
+                (Num(n1), Num(n2)) => Num(n1 - n2),
+                (e, Num(0.0)) => e,
+                (Num(0.0), e) => neg(e),
+                (e1, e2) if e1 == e2 => Num(0.0),
+                (Add(e1, e2), e3) if *e1 == e3 => *e2,
+                (Add(e1, e2), e3) if *e2 == e3 => *e1,
+                (e1, Neg(e2)) => add(e1, *e2),
+                (e, Num(n)) if n < 0.0 => add(e, Num(-n)),
+                (e1, e2) => sub(e1, e2),
+            },
+            Mul(e1, e2) => match (e1.simplify_once(), e2.simplify_once()) {
+                (Num(n1), Num(n2)) => Num(n1 * n2),
+                (Num(0.0), _) | (_, Num(0.0)) => Num(0.0),
+                (Num(1.0), e) | (e, Num(1.0)) => e,
+                (Num(-1.0), e) | (e, Num(-1.0)) => neg(e),
+                (Neg(e1), e2) => neg(mul(*e1, e2)),
+                (e1, Neg(e2)) => neg(mul(e1, *e2)),
+                (e, Num(n)) => mul(Num(n), e), //constants first: `2*x`
+                (Num(n1), Mul(e1, e2)) => match *e1 {
+                    Num(n2) => mul(Num(n1 * n2), *e2),
+                    e1 => mul(Num(n1), mul(e1, *e2)),
+                },
+                (e1, Mul(e2, e3)) if matches!(*e2, Num(_)) => mul(*e2, mul(e1, *e3)),
+                (Div(e1, e2), e3) => div(mul(*e1, e3), *e2),
+                (e1, Div(e2, e3)) => div(mul(e1, *e2), *e3),
+                (e1, e2) if e1 == e2 => pow(e1, Num(2.0)),
+                (Pow(base, exponent), e) | (e, Pow(base, exponent)) if *base == e => pow(e, add(*exponent, Num(1.0))),
+                (Pow(base1, exponent1), Pow(base2, exponent2)) if base1 == base2 => pow(*base1, add(*exponent1, *exponent2)),
+                (e1, e2) => mul(e1, e2),
+            },
+            Div(e1, e2) => match (e1.simplify_once(), e2.simplify_once()) {
+                (Num(n1), Num(n2)) if n2 != 0.0 && (n1 / n2).fract() == 0.0 => Num(n1 / n2),
+                (Num(0.0), e) if e != Num(0.0) => Num(0.0),
+                (e, Num(1.0)) => e,
+                (e1, e2) if e1 == e2 && e1 != Num(0.0) => Num(1.0),
+                (Neg(e1), e2) => neg(div(*e1, e2)),
+                (e1, Neg(e2)) => neg(div(e1, *e2)),
+                (Div(e1, e2), e3) => div(*e1, mul(*e2, e3)),
+                (e1, Div(e2, e3)) => div(mul(e1, *e3), *e2),
+                (e1, e2) => div(e1, e2),
+            },
+            Pow(e1, e2) => match (e1.simplify_once(), e2.simplify_once()) {
+                (Num(n1), Num(n2)) if n1.powf(n2).is_finite() && n1.powf(n2).fract() == 0.0 => Num(n1.powf(n2)),
+                (_, Num(0.0)) => Num(1.0),
+                (e, Num(1.0)) => e,
+                (Num(1.0), _) => Num(1.0),
+                //only for an integer exponent: `(x^2)^0.5` is |x|, not x.
+                (Pow(base, exponent), Num(n)) if n.fract() == 0.0 => pow(*base, mul(*exponent, Num(n))),
+                (e1, e2) => pow(e1, e2),
+            },
+            Call(name, e) => call(name, e.simplify_once()),
+        }
+    }
+
+    fn precedence(&self) -> u8 {
+        match self {
+            Add(..) | Sub(..) => 1,
+            Mul(..) | Div(..) => 2,
+            Neg(_) => 3,
+            Num(n) if *n < 0.0 => 3,
+            Pow(..) => 4,
+            Num(_) | Var(_) | Call(..) => 5,
+        }
+    }
+
+    fn fmt_operand(&self, f: &mut Formatter<'_>, min_precedence: u8) -> std::fmt::Result {
+        if self.precedence() < min_precedence {
+            write!(f, "({})", self)
+        } else {
+            write!(f, "{}", self)
+        }
+    }
+
+    fn fmt_binary(f: &mut Formatter<'_>, e1: &Expression, min_precedence1: u8, op: &str, e2: &Expression, min_precedence2: u8) -> std::fmt::Result {
+        e1.fmt_operand(f, min_precedence1)?;
+        write!(f, "{}", op)?;
+        e2.fmt_operand(f, min_precedence2)
+    }
+}
+
+///As source text, with only the parentheses that are needed.
+///Note that a unary minus binds stronger than `^`, so `-(x^2)` keeps its parentheses.
+impl Display for Expression {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Num(n) => write!(f, "{}", n),
+            Var(name) => write!(f, "{}", name),
+            Neg(e) if matches!(**e, Pow(..)) => write!(f, "-({})", e),
+            Neg(e) => {
+                write!(f, "-")?;
+                e.fmt_operand(f, 2)
+            },
+            Add(e1, e2) => Expression::fmt_binary(f, e1, 1, " + ", e2, 1),
+            Sub(e1, e2) => Expression::fmt_binary(f, e1, 1, " - ", e2, 2),
+            Mul(e1, e2) => Expression::fmt_binary(f, e1, 2, "*", e2, 2),
+            Div(e1, e2) => Expression::fmt_binary(f, e1, 2, "/", e2, 4),
+            Pow(e1, e2) => Expression::fmt_binary(f, e1, 5, "^", e2, 5),
+            Call(name, e) => write!(f, "{}({})", name, e),
+        }
+    }
+}
@@ -1,18 +1,22 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 use chrono::{Datelike, Utc};
 use crate::resolver::scope::Scope;
-use crate::errors::Error;
+use crate::errors::{count_real_errors, Error};
 use crate::date::{Day, Timepoint};
 use crate::date::month_from_int;
 use crate::errors;
-use crate::parser::nodes::{CodeBlock, FunctionDefExpr};
-use crate::resolver::{add_error_value, Resolver};
+use crate::parser::nodes::{CodeBlock, FunctionDefExpr, Node};
+use crate::resolver::{add_error_value, explode_if_one_arg, Resolver};
 use crate::globals::Globals;
 use crate::number::Number;
 use crate::number_format::NumberFormat;
+use crate::complex::Complex;
+use crate::matrix::Matrix;
+use crate::resolver::operator::{op_num_approx_num, operands_range, operator_id_from, product_unit, to_si_doubles, OperatorType};
 use crate::resolver::recursive_iterator::recursive_iter;
+use crate::resolver::serialize::{format_complex, format_number};
 use crate::resolver::unit::{Unit, UnitProperty};
 use crate::resolver::value::Value;
 use crate::resolver::value::Variant;
@@ -44,6 +48,8 @@ pub struct CustomFunctionDef {
     pub execute: fn(local_function_def: &CustomFunctionDef, scope: &Rc<RefCell<Scope>>, args: &Vec<Value>, range: &Range, errors: &mut Vec<Error>, globals: &Globals) -> Value,
     pub code_block: CodeBlock,
     pub function_def_expr: FunctionDefExpr,
+    pub default_values: Vec<Box<Node>>, //for the last fixed args.
+    pub depth: Cell<usize>, //number of active calls of this function, to save the variables of the calling frame.
 }
 
 impl FunctionDef for GlobalFunctionDef {
@@ -60,7 +66,7 @@ impl FunctionDef for GlobalFunctionDef {
         if self.is_correct_arg_count(args.len()) {
             (self.execute)(&self, scope, args, range, errors, globals)
         } else {
-            add_error_value(errors, errors::func_arg_wrong("", range.clone()))
+            add_error_value(errors, errors::func_arg_wrong(&self.name, range.clone()))
         }
     }
 
@@ -87,7 +93,7 @@ impl FunctionDef for CustomFunctionDef {
         if self.is_correct_arg_count(args.len()) {
             (self.execute)(&self, scope, args, range, errors, globals)
         } else {
-            add_error_value(errors, errors::func_arg_wrong("", range.clone()))
+            add_error_value(errors, errors::func_arg_wrong(&self.name, range.clone()))
         }
     }
 
@@ -167,9 +173,40 @@ pub fn create_global_function_defs() -> HashMap<String, GlobalFunctionDef> {
         ("first".to_string(), GlobalFunctionDef { name: "first".to_string(), min_args: 2, max_args: 999, execute: first, func_type: FunctionType::Arithm}),
         ("last".to_string(), GlobalFunctionDef { name: "last".to_string(), min_args: 2, max_args: 999, execute: last, func_type: FunctionType::Arithm}),
 
+        ("map".to_string(), GlobalFunctionDef { name: "map".to_string(), min_args: 2, max_args: 2, execute: map, func_type: FunctionType::Arithm}),
+        ("filter".to_string(), GlobalFunctionDef { name: "filter".to_string(), min_args: 2, max_args: 2, execute: filter, func_type: FunctionType::Arithm}),
+        ("reduce".to_string(), GlobalFunctionDef { name: "reduce".to_string(), min_args: 2, max_args: 3, execute: reduce, func_type: FunctionType::Arithm}),
+        ("count".to_string(), GlobalFunctionDef { name: "count".to_string(), min_args: 2, max_args: 2, execute: count, func_type: FunctionType::Arithm}),
+
+        ("dot".to_string(), GlobalFunctionDef { name: "dot".to_string(), min_args: 2, max_args: 2, execute: dot, func_type: FunctionType::Arithm}),
+        ("cross".to_string(), GlobalFunctionDef { name: "cross".to_string(), min_args: 2, max_args: 2, execute: cross, func_type: FunctionType::Arithm}),
+        ("norm".to_string(), GlobalFunctionDef { name: "norm".to_string(), min_args: 1, max_args: 1, execute: norm, func_type: FunctionType::Arithm}),
+        ("normalize".to_string(), GlobalFunctionDef { name: "normalize".to_string(), min_args: 1, max_args: 1, execute: normalize, func_type: FunctionType::Arithm}),
+
+        ("transpose".to_string(), GlobalFunctionDef { name: "transpose".to_string(), min_args: 1, max_args: 1, execute: transpose, func_type: FunctionType::Arithm}),
+        ("det".to_string(), GlobalFunctionDef { name: "det".to_string(), min_args: 1, max_args: 1, execute: det, func_type: FunctionType::Arithm}),
+        ("inverse".to_string(), GlobalFunctionDef { name: "inverse".to_string(), min_args: 1, max_args: 1, execute: inverse, func_type: FunctionType::Arithm}),
+        ("solve".to_string(), GlobalFunctionDef { name: "solve".to_string(), min_args: 2, max_args: 2, execute: solve, func_type: FunctionType::Arithm}),
+
+        ("derive".to_string(), GlobalFunctionDef { name: "derive".to_string(), min_args: 2, max_args: 2, execute: symbolic_by_ref, func_type: FunctionType::Arithm}),
+        ("simplify".to_string(), GlobalFunctionDef { name: "simplify".to_string(), min_args: 1, max_args: 1, execute: symbolic_by_ref, func_type: FunctionType::Arithm}),
+        ("integrate".to_string(), GlobalFunctionDef { name: "integrate".to_string(), min_args: 3, max_args: 3, execute: integrate, func_type: FunctionType::Arithm}),
+        ("diff".to_string(), GlobalFunctionDef { name: "diff".to_string(), min_args: 2, max_args: 2, execute: diff, func_type: FunctionType::Arithm}),
+        ("re".to_string(), GlobalFunctionDef { name: "re".to_string(), min_args: 1, max_args: 1, execute: re, func_type: FunctionType::Arithm}),
+        ("im".to_string(), GlobalFunctionDef { name: "im".to_string(), min_args: 1, max_args: 1, execute: im, func_type: FunctionType::Arithm}),
+        ("arg".to_string(), GlobalFunctionDef { name: "arg".to_string(), min_args: 1, max_args: 1, execute: arg, func_type: FunctionType::Arithm}),
+        ("conj".to_string(), GlobalFunctionDef { name: "conj".to_string(), min_args: 1, max_args: 1, execute: conj, func_type: FunctionType::Arithm}),
+
+        ("str".to_string(), GlobalFunctionDef { name: "str".to_string(), min_args: 1, max_args: 1, execute: str_func, func_type: FunctionType::Arithm}),
+        ("format".to_string(), GlobalFunctionDef { name: "format".to_string(), min_args: 2, max_args: 2, execute: format_func, func_type: FunctionType::Arithm}),
+
         ("factors".to_string(), GlobalFunctionDef { name: "factors".to_string(), min_args: 1, max_args: 1, execute: factors, func_type: FunctionType::Arithm}),
         ("primes".to_string(), GlobalFunctionDef { name: "primes".to_string(), min_args: 1, max_args: 1, execute: primes, func_type: FunctionType::Arithm}),
 
+        ("assert".to_string(), GlobalFunctionDef { name: "assert".to_string(), min_args: 1, max_args: 1, execute: assert, func_type: FunctionType::Arithm}),
+        ("assert_eq".to_string(), GlobalFunctionDef { name: "assert_eq".to_string(), min_args: 2, max_args: 2, execute: assert_eq, func_type: FunctionType::Arithm}),
+        ("assert_approx".to_string(), GlobalFunctionDef { name: "assert_approx".to_string(), min_args: 3, max_args: 3, execute: assert_approx, func_type: FunctionType::Arithm}),
+
         ("now".to_string(), GlobalFunctionDef { name: "now".to_string(), min_args: 0, max_args: 0, execute: now, func_type: FunctionType::Date}),
         ("date".to_string(), GlobalFunctionDef { name: "date".to_string(), min_args: 3, max_args: 3, execute: date_func, func_type: FunctionType::Date}),
     ]);
@@ -178,6 +215,9 @@ pub fn create_global_function_defs() -> HashMap<String, GlobalFunctionDef> {
 
 
 fn abs(global_function_def: &GlobalFunctionDef, _scope: &Rc<RefCell<Scope>>, args: &Vec<Value>, range: &Range, errors: &mut Vec<Error>, _globals: &Globals) -> Value {
+    if let Variant::Complex { complex } = &args[0].variant {
+        return Value::from_number(Number::from(complex.abs()), range.clone());
+    }
     let Some(number) = match_arg_number(global_function_def, &args[0], range, errors) else { return Value::error(range.clone()); };
     Value::from_number(Number {significand: number.significand.abs(), exponent: number.exponent, unit: number.unit.clone(), fmt: NumberFormat::Dec }, range.clone())
 }
@@ -197,8 +237,14 @@ fn ceil(global_function_def: &GlobalFunctionDef, _scope: &Rc<RefCell<Scope>>, ar
     let Some(number) = match_arg_number(global_function_def, &args[0], range, errors) else { return Value::error(range.clone()); };
     Value::from_number(Number {significand: number.to_double().ceil(), exponent: 0, unit: number.unit.clone(), fmt: NumberFormat::Dec }, range.clone())
 }
-fn sqrt(global_function_def: &GlobalFunctionDef, _scope: &Rc<RefCell<Scope>>, args: &Vec<Value>, range: &Range, errors: &mut Vec<Error>, _globals: &Globals) -> Value {
+fn sqrt(global_function_def: &GlobalFunctionDef, scope: &Rc<RefCell<Scope>>, args: &Vec<Value>, range: &Range, errors: &mut Vec<Error>, _globals: &Globals) -> Value {
+    if let Variant::Complex { complex } = &args[0].variant {
+        return Value::from_complex(complex.sqrt(), range.clone());
+    }
     let Some(number) = match_arg_number(global_function_def, &args[0], range, errors) else { return Value::error(range.clone()); };
+    if number.to_double() < 0.0 {
+        return Value::from_complex(Complex::new(0.0, (-number.to_double()).sqrt(), scope.borrow().imaginary_unit()), range.clone());
+    }
     Value::from_number(Number {significand: number.to_double().sqrt(), exponent: 0, unit: number.unit.clone(), fmt: NumberFormat::Dec }, range.clone())
 }
 fn max(global_function_def: &GlobalFunctionDef, _scope: &Rc<RefCell<Scope>>, args: &Vec<Value>, range: &Range, errors: &mut Vec<Error>, globals: &Globals) -> Value {
@@ -229,6 +275,7 @@ fn reverse(_global_function_def: &GlobalFunctionDef, _scope: &Rc<RefCell<Scope>>
         stmt_range: range.clone(),
         variant: Variant::List { values: reversed },
         has_errors: false,
+        echo: None,
     }
 }
 
@@ -248,6 +295,7 @@ fn sort(_global_function_def: &GlobalFunctionDef, _scope: &Rc<RefCell<Scope>>, a
         stmt_range: range.clone(),
         variant: Variant::List { values: sorted },
         has_errors: false,
+        echo: None,
     }
 }
 
@@ -278,6 +326,7 @@ fn floats_to_values(range: &Range, factors: &Vec<f64>) -> Vec<Value> {
                     },
                 },
                 has_errors: false,
+                echo: None,
             }
         )
         .collect();
@@ -293,6 +342,7 @@ fn factors(global_function_def: &GlobalFunctionDef, _scope: &Rc<RefCell<Scope>>,
         stmt_range: range.clone(),
         variant: Variant::List { values: list },
         has_errors: false,
+        echo: None,
     }
 }
 
@@ -308,6 +358,7 @@ fn primes(global_function_def: &GlobalFunctionDef, _scope: &Rc<RefCell<Scope>>,
         stmt_range: range.clone(),
         variant: Variant::List { values: list },
         has_errors: false,
+        echo: None,
     }
 }
 
@@ -339,6 +390,234 @@ fn last(_global_function_def: &GlobalFunctionDef, _scope: &Rc<RefCell<Scope>>, a
     args.last().unwrap().clone() //unwrap: arg cnt checked.
 }
 
+fn match_arg_list<'a>(function_def: &dyn FunctionDef, arg: &'a Value, range: &Range, errors: &mut Vec<Error>) -> Option<&'a Vec<Value>> {
+    let Variant::List { values } = &arg.variant else {
+        errors.push(errors::func_arg_wrong_type(function_def.get_name(), "The first argument must be a list.", range.clone()));
+        return None;
+    };
+    Some(values)
+}
+
+fn match_arg_function(function_def: &dyn FunctionDef, arg: &Value, range: &Range, errors: &mut Vec<Error>) -> bool {
+    let Variant::FunctionRef { .. } = &arg.variant else {
+        errors.push(errors::func_arg_wrong_type(function_def.get_name(), "Expected a function, like `x => x*2`.", range.clone()));
+        return false;
+    };
+    true
+}
+
+///Calls the predicate for every value of the list and returns the values for which it's true.
+fn filter_values(function_def: &dyn FunctionDef, values: &Vec<Value>, predicate: &Value, range: &Range, errors: &mut Vec<Error>, globals: &Globals) -> Option<Vec<Value>> {
+    let mut filtered = Vec::new();
+    for value in values {
+        let result = call_function_ref(predicate, &vec![value.clone()], range, errors, globals);
+        if result.has_errors {
+            return None;
+        }
+        let Variant::Bool { value: true_or_false } = result.variant else {
+            errors.push(errors::func_arg_wrong_type(function_def.get_name(), "The function must return a boolean value.", range.clone()));
+            return None;
+        };
+        if true_or_false {
+            filtered.push(value.clone());
+        }
+    }
+    Some(filtered)
+}
+
+fn map(global_function_def: &GlobalFunctionDef, _scope: &Rc<RefCell<Scope>>, args: &Vec<Value>, range: &Range, errors: &mut Vec<Error>, globals: &Globals) -> Value {
+    let Some(values) = match_arg_list(global_function_def, &args[0], range, errors) else { return Value::error(range.clone()); };
+    if !match_arg_function(global_function_def, &args[1], range, errors) { return Value::error(range.clone()); }
+    let mut mapped = Vec::new();
+    for value in values {
+        let result = call_function_ref(&args[1], &vec![value.clone()], range, errors, globals);
+        if result.has_errors {
+            return Value::error(range.clone());
+        }
+        mapped.push(result);
+    }
+    Value::from_list(mapped, range.clone())
+}
+
+fn filter(global_function_def: &GlobalFunctionDef, _scope: &Rc<RefCell<Scope>>, args: &Vec<Value>, range: &Range, errors: &mut Vec<Error>, globals: &Globals) -> Value {
+    let Some(values) = match_arg_list(global_function_def, &args[0], range, errors) else { return Value::error(range.clone()); };
+    if !match_arg_function(global_function_def, &args[1], range, errors) { return Value::error(range.clone()); }
+    let Some(filtered) = filter_values(global_function_def, values, &args[1], range, errors, globals) else { return Value::error(range.clone()); };
+    Value::from_list(filtered, range.clone())
+}
+
+fn count(global_function_def: &GlobalFunctionDef, _scope: &Rc<RefCell<Scope>>, args: &Vec<Value>, range: &Range, errors: &mut Vec<Error>, globals: &Globals) -> Value {
+    let Some(values) = match_arg_list(global_function_def, &args[0], range, errors) else { return Value::error(range.clone()); };
+    if !match_arg_function(global_function_def, &args[1], range, errors) { return Value::error(range.clone()); }
+    let Some(filtered) = filter_values(global_function_def, values, &args[1], range, errors, globals) else { return Value::error(range.clone()); };
+    Value::from_number(Number {significand: filtered.len() as f64, exponent: 0, unit: Unit::none(), fmt: NumberFormat::Dec }, range.clone())
+}
+
+fn str_func(global_function_def: &GlobalFunctionDef, scope: &Rc<RefCell<Scope>>, args: &Vec<Value>, range: &Range, errors: &mut Vec<Error>, _globals: &Globals) -> Value {
+    let Some(text) = to_text(&args[0], scope.borrow().precision) else {
+        return add_error_value(errors, errors::func_arg_wrong_type(global_function_def.get_name(), "Expected a number, date, bool, text or a list of these.", range.clone()));
+    };
+    Value::from_text(text, range.clone())
+}
+
+pub(crate) fn to_text(value: &Value, precision: f64) -> Option<String> {
+    match &value.variant {
+        Variant::Numeric { number } => Some(format!("{}{}", format_number(number, precision), number.unit.id)),
+        Variant::Date { date } => Some(date.to_formatted_string()),
+        Variant::Bool { value } => Some(value.to_string()),
+        Variant::Text { text } => Some(text.clone()),
+        Variant::List { values } => values.iter()
+            .map(|value| to_text(value, precision))
+            .collect::<Option<Vec<String>>>()
+            .map(|texts| texts.join(", ")),
+        Variant::Record { fields } => fields.iter()
+            .map(|(name, value)| to_text(value, precision).map(|text| format!("{name}: {text}")))
+            .collect::<Option<Vec<String>>>()
+            .map(|texts| format!("{{{}}}", texts.join(", "))),
+        Variant::Matrix { matrix, unit } => Some(format!("[{}]", matrix_rows_text(matrix, unit, precision).join("; "))),
+        Variant::Complex { complex } => Some(format_complex(complex, precision)),
+        Variant::Expression { expression } => Some(expression.to_string()),
+        _ => None
+    }
+}
+
+pub(crate) const ASSERT_FUNCTIONS: [&str; 3] = ["assert", "assert_eq", "assert_approx"];
+
+fn assert(global_function_def: &GlobalFunctionDef, _scope: &Rc<RefCell<Scope>>, args: &Vec<Value>, range: &Range, errors: &mut Vec<Error>, globals: &Globals) -> Value {
+    let Variant::Bool { value } = &args[0].variant else {
+        return add_error_value(errors, errors::func_arg_wrong_type(global_function_def.get_name(), "Expected a bool.", range.clone()));
+    };
+    if !*value {
+        return add_error_value(errors, errors::assert_failed(globals.get_text(range), range.clone()));
+    }
+    Value::from_bool(true, range.clone())
+}
+
+fn assert_eq(_global_function_def: &GlobalFunctionDef, scope: &Rc<RefCell<Scope>>, args: &Vec<Value>, range: &Range, errors: &mut Vec<Error>, globals: &Globals) -> Value {
+    let error_count = errors.len();
+    //values that can't be compared, e.g. for different unit properties, are not equal.
+    if !values_equal(&args[0], &args[1], range, errors, globals) || errors.len() != error_count {
+        let precision = scope.borrow().precision;
+        return add_error_value(errors, errors::assert_eq_failed(&assert_text(&args[0], precision), &assert_text(&args[1], precision), range.clone()));
+    }
+    Value::from_bool(true, range.clone())
+}
+
+fn assert_approx(global_function_def: &GlobalFunctionDef, scope: &Rc<RefCell<Scope>>, args: &Vec<Value>, range: &Range, errors: &mut Vec<Error>, globals: &Globals) -> Value {
+    if args.iter().any(|arg| !matches!(arg.variant, Variant::Numeric {..})) {
+        return add_error_value(errors, errors::func_arg_wrong_type(global_function_def.get_name(), "Expected numeric values.", range.clone()));
+    }
+    let error_count = errors.len();
+    let approx = op_num_approx_num(globals, args, range, errors);
+    if !matches!(approx.variant, Variant::Bool { value: true }) || errors.len() != error_count {
+        let precision = scope.borrow().precision;
+        return add_error_value(errors, errors::assert_approx_failed(&assert_text(&args[0], precision), &assert_text(&args[2], precision), &assert_text(&args[1], precision), range.clone()));
+    }
+    Value::from_bool(true, range.clone())
+}
+
+///Lists are equal if all their elements are equal, records if all their fields are equal, in any order. Other values are compared with the `==` operator, if one exists for both types.
+fn values_equal(value1: &Value, value2: &Value, range: &Range, errors: &mut Vec<Error>, globals: &Globals) -> bool {
+    if let (Variant::List { values: values1 }, Variant::List { values: values2 }) = (&value1.variant, &value2.variant) {
+        return values1.len() == values2.len()
+            && values1.iter().zip(values2.iter()).all(|(v1, v2)| values_equal(v1, v2, range, errors, globals));
+    }
+    if let (Variant::Record { fields: fields1 }, Variant::Record { fields: fields2 }) = (&value1.variant, &value2.variant) {
+        return fields1.len() == fields2.len()
+            && fields1.iter().all(|(name1, v1)| fields2.iter().any(|(name2, v2)| name1 == name2 && values_equal(v1, v2, range, errors, globals)));
+    }
+    let op_id = operator_id_from(value1.variant.to_operand_type(), OperatorType::Equal, value2.variant.to_operand_type());
+    let Some(op) = globals.get_operator(op_id) else {
+        return false;
+    };
+    matches!(op(globals, &vec![value1.clone(), value2.clone()], range, errors).variant, Variant::Bool { value: true })
+}
+
+fn assert_text(value: &Value, precision: f64) -> String {
+    to_text(value, precision).unwrap_or_else(|| value.variant.name().to_string())
+}
+
+fn matrix_rows_text(matrix: &Matrix, unit: &Unit, precision: f64) -> Vec<String> {
+    (0..matrix.rows)
+        .map(|row| matrix.row(row).iter().map(|v| format!("{}{}", format_number(&Number::from(*v), precision), unit.id)).collect::<Vec<String>>().join(", "))
+        .collect()
+}
+
+///format(number, pattern): the pattern is a notation (`dec`, `hex`, `oct`, `bin`, `exp`) or a number of decimals, like `0.00`.
+fn format_func(global_function_def: &GlobalFunctionDef, scope: &Rc<RefCell<Scope>>, args: &Vec<Value>, range: &Range, errors: &mut Vec<Error>, _globals: &Globals) -> Value {
+    let Some(number) = match_arg_number(global_function_def, &args[0], range, errors) else {
+        return Value::error(range.clone());
+    };
+    let Variant::Text { text: pattern } = &args[1].variant else {
+        return add_error_value(errors, errors::func_arg_wrong_type(global_function_def.get_name(), "The pattern must be a text.", range.clone()));
+    };
+    let fmt = match pattern.as_str() {
+        "dec" => Some(NumberFormat::Dec),
+        "hex" => Some(NumberFormat::Hex),
+        "oct" => Some(NumberFormat::Oct),
+        "bin" => Some(NumberFormat::Bin),
+        "exp" => Some(NumberFormat::Exp),
+        _ => None
+    };
+    let formatted = if let Some(fmt) = fmt {
+        format_number(&Number { fmt, ..number.clone() }, scope.borrow().precision)
+    } else {
+        let decimals = match pattern.split_once('.') {
+            None if pattern == "0" => 0,
+            Some(("0", zeros)) if !zeros.is_empty() && zeros.chars().all(|c| c == '0') => zeros.len(),
+            _ => {
+                let info = format!("Unknown pattern `{}`. Use dec, hex, oct, bin, exp or a number of decimals like 0.00", pattern);
+                return add_error_value(errors, errors::func_arg_wrong_type(global_function_def.get_name(), &info, range.clone()));
+            }
+        };
+        format!("{:.*}", decimals, number.to_double())
+    };
+    Value::from_text(format!("{}{}", formatted, number.unit.id), range.clone())
+}
+
+///reduce(list, f, init): without init, the first value of the list is used.
+fn reduce(global_function_def: &GlobalFunctionDef, _scope: &Rc<RefCell<Scope>>, args: &Vec<Value>, range: &Range, errors: &mut Vec<Error>, globals: &Globals) -> Value {
+    let Some(values) = match_arg_list(global_function_def, &args[0], range, errors) else { return Value::error(range.clone()); };
+    if !match_arg_function(global_function_def, &args[1], range, errors) { return Value::error(range.clone()); }
+    let mut values = values.iter();
+    let Some(mut accumulator) = args.get(2).or_else(|| values.next()).cloned() else {
+        errors.push(errors::func_arg_wrong_type(global_function_def.get_name(), "Can't reduce an empty list without an initial value.", range.clone()));
+        return Value::error(range.clone());
+    };
+    for value in values {
+        accumulator = call_function_ref(&args[1], &vec![accumulator, value.clone()], range, errors, globals);
+        if accumulator.has_errors {
+            return Value::error(range.clone());
+        }
+    }
+    accumulator
+}
+
+///Calls a named function or lambda that is passed as a value.
+pub fn call_function_ref(function_ref: &Value, args: &Vec<Value>, range: &Range, errors: &mut Vec<Error>, globals: &Globals) -> Value {
+    let Variant::FunctionRef { name, scope, captures } = &function_ref.variant else {
+        return add_error_value(errors, errors::func_arg_wrong_type("", "Expected a function.", range.clone()));
+    };
+    let Some(scope) = scope.upgrade() else {
+        return add_error_value(errors, errors::func_not_def(name, range.clone()));
+    };
+    let overload_id = scope.borrow().overload_id(name, args);
+    let function_id = match overload_id {
+        Ok(function_id) => function_id,
+        Err(arities) => return add_error_value(errors, errors::func_no_overload(name, &arities, range.clone())),
+    };
+    let mut call = || Scope::with_function(&scope, &function_id, globals, |fd| {
+        let mut exploded_args = Vec::new();
+        let args = if fd.get_min_args() > 1 { explode_if_one_arg(args, &mut exploded_args) } else { args };
+        fd.call(&scope, args, range, errors, globals)
+    });
+    let result = match Scope::function_body_scope(&scope, &function_id) {
+        Some(body_scope) if !captures.is_empty() => Scope::with_captures(&body_scope, captures, call),
+        _ => call(),
+    };
+    result.unwrap_or_else(|| add_error_value(errors, errors::func_not_def(name, range.clone())))
+}
+
 fn match_arg_number<'a>(function_def: &dyn FunctionDef, args: &'a Value, range: &Range, errors: &mut Vec<Error>) -> Option<&'a Number> {
     let Variant::Numeric { number, .. } = &args.variant else {
         errors.push(errors::func_arg_wrong_type(function_def.get_name(), "must be numeric", range.clone()));
@@ -440,16 +719,58 @@ fn factorial(global_function_def: &GlobalFunctionDef, _scope: &Rc<RefCell<Scope>
     Value::from_number(Number {significand: val as f64, exponent: 0, unit: number.unit.clone(), fmt: NumberFormat::Dec }, range.clone())
 }
 
-pub fn execute_custom_function(local_function_def: &CustomFunctionDef, _scope: &Rc<RefCell<Scope>>, args: &Vec<Value>, range: &Range, errors: &mut Vec<Error>, globals: &Globals) -> Value {
+pub fn execute_custom_function(local_function_def: &CustomFunctionDef, scope: &Rc<RefCell<Scope>>, args: &Vec<Value>, range: &Range, errors: &mut Vec<Error>, globals: &Globals) -> Value {
+    let call_depth = globals.call_depth.get();
+    let max_recursion = scope.borrow().max_recursion;
+    if call_depth >= max_recursion {
+        return add_error_value(errors, errors::recursion_too_deep(&local_function_def.name, &max_recursion.to_string(), range.clone()));
+    }
+    let depth = local_function_def.depth.get();
+    //a recursive call gets its own variables. Those of the calling frame are restored afterwards.
+    let function_scope = &local_function_def.code_block.scope;
+    let frame_variables = if depth > 0 {
+        Some(std::mem::take(&mut function_scope.borrow_mut().variables))
+    } else {
+        None
+    };
+    local_function_def.depth.set(depth + 1);
+    globals.call_depth.set(call_depth + 1);
+    let result = execute_function_body(local_function_def, args, range, errors, globals);
+    globals.call_depth.set(call_depth);
+    local_function_def.depth.set(depth);
+    if let Some(frame_variables) = frame_variables {
+        function_scope.borrow_mut().variables = frame_variables;
+    }
+    result
+}
+
+fn execute_function_body(local_function_def: &CustomFunctionDef, args: &Vec<Value>, range: &Range, errors: &mut Vec<Error>, globals: &Globals) -> Value {
     let mut param_variables = HashMap::<String, Value>::new();
+    let arg_names = &local_function_def.function_def_expr.arg_names;
+    let fixed_args = arg_names.len() - local_function_def.function_def_expr.variadic as usize;
 
     //Note that number of args has already been checked in call()
-    for (i, arg) in args.iter().enumerate() {
-        param_variables.insert(local_function_def.function_def_expr.arg_names[i].clone(), arg.clone());
+    for (i, arg) in args.iter().take(fixed_args).enumerate() {
+        param_variables.insert(arg_names[i].clone(), arg.clone());
+    }
+    if local_function_def.function_def_expr.variadic {
+        let rest = args.iter().skip(fixed_args).cloned().collect();
+        param_variables.insert(arg_names[fixed_args].clone(), Value::from_list(rest, range.clone()));
     }
 
     local_function_def.code_block.scope.borrow_mut().variables.extend(param_variables);
-    let mut resolver = Resolver {globals, scope: local_function_def.code_block.scope.clone(), results: Vec::new(), errors, muted: true, current_statement_muted: false};
+    let mut resolver = Resolver {globals, scope: local_function_def.code_block.scope.clone(), results: Vec::new(), errors, muted: true, current_statement_muted: false, last_result: None, echo_results: Vec::new(), line_results: Vec::new()};
+
+    //missing args get their default value, resolved within the function so that they can refer to previous args.
+    let first_default = fixed_args - local_function_def.default_values.len();
+    let missing_args = arg_names.iter().take(fixed_args).skip(args.len());
+    for (arg_name, default_value) in missing_args.zip(local_function_def.default_values.iter().skip(args.len() - first_default)) {
+        let value = resolver.resolve_node(default_value);
+        if value.has_errors {
+            return Value::error(range.clone());
+        }
+        local_function_def.code_block.scope.borrow_mut().variables.insert(arg_name.clone(), value);
+    }
     let result = resolver.resolve_to_result(&local_function_def.code_block.statements);
     let Some(result) = result else {
         errors.push(errors::func_no_body(&local_function_def.name, range.clone()));
@@ -527,6 +848,261 @@ fn flatten(_global_function_def: &GlobalFunctionDef, _scope: &Rc<RefCell<Scope>>
 }
 
 #[inline]
+fn dot(global_function_def: &GlobalFunctionDef, _scope: &Rc<RefCell<Scope>>, args: &Vec<Value>, range: &Range, errors: &mut Vec<Error>, globals: &Globals) -> Value {
+    let (v1, unit1) = match to_vector(global_function_def, &args[0], range, errors, globals) { Ok(vector) => vector, Err(error_value) => return error_value };
+    let (v2, unit2) = match to_vector(global_function_def, &args[1], range, errors, globals) { Ok(vector) => vector, Err(error_value) => return error_value };
+    if v1.len() != v2.len() {
+        return add_error_value(errors, errors::list_size_diff(&v1.len().to_string(), &v2.len().to_string(), operands_range(&args[0], &args[1], range)));
+    }
+    let product = v1.iter().zip(v2.iter()).map(|(d1, d2)| d1 * d2).sum();
+    Value::from_number(Number { significand: product, exponent: 0, unit: product_unit(unit1, unit2), fmt: NumberFormat::Dec }, range.clone())
+}
+
+fn cross(global_function_def: &GlobalFunctionDef, _scope: &Rc<RefCell<Scope>>, args: &Vec<Value>, range: &Range, errors: &mut Vec<Error>, globals: &Globals) -> Value {
+    let (v1, unit1) = match to_vector(global_function_def, &args[0], range, errors, globals) { Ok(vector) => vector, Err(error_value) => return error_value };
+    let (v2, unit2) = match to_vector(global_function_def, &args[1], range, errors, globals) { Ok(vector) => vector, Err(error_value) => return error_value };
+    let ([x1, y1, z1], [x2, y2, z2]) = (v1.as_slice(), v2.as_slice()) else {
+        let operand = if v1.len() != 3 { &args[0] } else { &args[1] };
+        return add_error_value(errors, errors::func_arg_wrong_type(global_function_def.get_name(), "Expected two lists of 3 numbers.", operand.stmt_range.clone()));
+    };
+    let unit = product_unit(unit1, unit2);
+    let values = [y1*z2 - z1*y2, z1*x2 - x1*z2, x1*y2 - y1*x2].iter()
+        .map(|d| Value::from_number(Number { significand: *d, exponent: 0, unit: unit.clone(), fmt: NumberFormat::Dec }, range.clone()))
+        .collect();
+    Value::from_list(values, range.clone())
+}
+
+fn norm(global_function_def: &GlobalFunctionDef, _scope: &Rc<RefCell<Scope>>, args: &Vec<Value>, range: &Range, errors: &mut Vec<Error>, globals: &Globals) -> Value {
+    let (vector, unit) = match to_vector(global_function_def, &args[0], range, errors, globals) { Ok(vector) => vector, Err(error_value) => return error_value };
+    let length = vector.iter().map(|d| d * d).sum::<f64>().sqrt();
+    Value::from_number(Number { significand: length, exponent: 0, unit, fmt: NumberFormat::Dec }, range.clone())
+}
+
+fn normalize(global_function_def: &GlobalFunctionDef, _scope: &Rc<RefCell<Scope>>, args: &Vec<Value>, range: &Range, errors: &mut Vec<Error>, globals: &Globals) -> Value {
+    let (vector, _) = match to_vector(global_function_def, &args[0], range, errors, globals) { Ok(vector) => vector, Err(error_value) => return error_value };
+    let length = vector.iter().map(|d| d * d).sum::<f64>().sqrt();
+    if length == 0.0 {
+        return add_error_value(errors, errors::func_arg_wrong_type(global_function_def.get_name(), "A zero vector has no direction.", range.clone()));
+    }
+    let values = vector.iter()
+        .map(|d| Value::from_number(Number::from(d / length), range.clone()))
+        .collect();
+    Value::from_list(values, range.clone())
+}
+
+fn transpose(global_function_def: &GlobalFunctionDef, _scope: &Rc<RefCell<Scope>>, args: &Vec<Value>, range: &Range, errors: &mut Vec<Error>, _globals: &Globals) -> Value {
+    let Some((matrix, unit)) = match_arg_matrix(global_function_def, &args[0], range, errors) else { return Value::error(range.clone()); };
+    Value::from_matrix(matrix.transpose(), unit.clone(), range.clone())
+}
+
+fn det(global_function_def: &GlobalFunctionDef, _scope: &Rc<RefCell<Scope>>, args: &Vec<Value>, range: &Range, errors: &mut Vec<Error>, _globals: &Globals) -> Value {
+    let Some(matrix) = match_arg_square_matrix(global_function_def, &args[0], range, errors) else { return Value::error(range.clone()); };
+    Value::from_number(Number::from(matrix.det()), range.clone())
+}
+
+fn inverse(global_function_def: &GlobalFunctionDef, _scope: &Rc<RefCell<Scope>>, args: &Vec<Value>, range: &Range, errors: &mut Vec<Error>, _globals: &Globals) -> Value {
+    let Some(matrix) = match_arg_square_matrix(global_function_def, &args[0], range, errors) else { return Value::error(range.clone()); };
+    let Some(inverse) = matrix.inverse() else {
+        return add_error_value(errors, errors::matrix_singular(range.clone()));
+    };
+    //the inverse of a matrix with a unit would have the inverse unit, which doesn't exist.
+    Value::from_matrix(inverse, Unit::none(), range.clone())
+}
+
+///solve(A, b): the x in `A*x = b`, with b a list.
+fn solve(global_function_def: &GlobalFunctionDef, _scope: &Rc<RefCell<Scope>>, args: &Vec<Value>, range: &Range, errors: &mut Vec<Error>, globals: &Globals) -> Value {
+    let Some(matrix) = match_arg_square_matrix(global_function_def, &args[0], range, errors) else { return Value::error(range.clone()); };
+    let Some(b) = to_si_doubles(&args[1], globals) else {
+        return add_error_value(errors, errors::func_arg_wrong_type(global_function_def.get_name(), "Expected a list of numbers as the second argument.", range.clone()));
+    };
+    if b.len() != matrix.rows {
+        return add_error_value(errors, errors::matrix_size_diff(&matrix.size_text(), &format!("{}x1", b.len()), range.clone()));
+    }
+    let Some(x) = matrix.solve(&b) else {
+        return add_error_value(errors, errors::matrix_singular(range.clone()));
+    };
+    Value::from_list(x.into_iter().map(|d| Value::from_number(Number::from(d), range.clone())).collect(), range.clone())
+}
+
+///`derive()` and `simplify()` are resolved with their expression unevaluated, which is only possible in a direct call.
+fn symbolic_by_ref(global_function_def: &GlobalFunctionDef, _scope: &Rc<RefCell<Scope>>, _args: &Vec<Value>, range: &Range, errors: &mut Vec<Error>, _globals: &Globals) -> Value {
+    add_error_value(errors, errors::func_arg_wrong_type(global_function_def.get_name(), "The expression can't be evaluated first: call the function directly.", range.clone()))
+}
+
+const MAX_SIMPSON_DEPTH: usize = 40;
+const MIN_SIMPSON_DEPTH: usize = 4; //a coarse estimate can look right by accident, e.g. for a step function.
+const SIMPSON_SCALE_SAMPLES: usize = 32;
+
+///integrate(f, a, b), with adaptive Simpson. x has the unit of the bounds.
+///As there are no combined units, the result has the unit of f, or else the unit of the bounds.
+fn integrate(global_function_def: &GlobalFunctionDef, scope: &Rc<RefCell<Scope>>, args: &Vec<Value>, range: &Range, errors: &mut Vec<Error>, globals: &Globals) -> Value {
+    if !match_arg_function(global_function_def, &args[0], range, errors) { return Value::error(range.clone()); }
+    let Some(lower) = match_arg_number(global_function_def, &args[1], range, errors) else { return Value::error(range.clone()); };
+    let Some(upper) = match_arg_number(global_function_def, &args[2], range, errors) else { return Value::error(range.clone()); };
+    let mut upper = upper.clone();
+    if !lower.unit.is_empty() && !upper.unit.is_empty() {
+        upper.convert_to_unit(&lower.unit, &scope.borrow().units_view, range, errors, globals);
+    }
+    let x_unit = if lower.unit.is_empty() { upper.unit.clone() } else { lower.unit.clone() };
+    let mut f_unit = None;
+    let integral = adaptive_simpson(&mut |x| call_with_number(global_function_def, &args[0], number_with_unit(x, &x_unit), &mut f_unit, range, errors, globals), lower.to_double(), upper.to_double());
+    let Some(integral) = integral else { return Value::error(range.clone()); };
+    let unit = f_unit.filter(|unit| !unit.is_empty()).unwrap_or(x_unit);
+    finite_number_or_error(global_function_def, integral, unit, range, errors)
+}
+
+fn adaptive_simpson(f: &mut impl FnMut(f64) -> Option<f64>, a: f64, b: f64) -> Option<f64> {
+    //the tolerance is relative to the size of f, which the 3 points of the first step could easily miss, as for sin(x) from 0 to 2*PI.
+    let mut max_f: f64 = 0.0;
+    for i in 0..=SIMPSON_SCALE_SAMPLES {
+        max_f = max_f.max(f(a + (b - a) * i as f64 / SIMPSON_SCALE_SAMPLES as f64)?.abs());
+    }
+    let tolerance = 1e-9 * (b - a).abs() * if max_f == 0.0 { 1.0 } else { max_f };
+    let m = a + (b - a) / 2.0;
+    let (fa, fm, fb) = (f(a)?, f(m)?, f(b)?);
+    let whole = (b - a) / 6.0 * (fa + 4.0 * fm + fb);
+    simpson_step(f, (a, fa), (m, fm), (b, fb), whole, tolerance, MAX_SIMPSON_DEPTH)
+}
+
+///Splits the interval in two, until the two halves add up to the whole, within the tolerance.
+fn simpson_step(f: &mut impl FnMut(f64) -> Option<f64>, (a, fa): (f64, f64), (m, fm): (f64, f64), (b, fb): (f64, f64), whole: f64, tolerance: f64, depth: usize) -> Option<f64> {
+    let (left_m, right_m) = (a + (m - a) / 2.0, m + (b - m) / 2.0);
+    let (f_left_m, f_right_m) = (f(left_m)?, f(right_m)?);
+    let left = (m - a) / 6.0 * (fa + 4.0 * f_left_m + fm);
+    let right = (b - m) / 6.0 * (fm + 4.0 * f_right_m + fb);
+    let delta = left + right - whole;
+    //a delta that isn't finite won't get any smaller.
+    if depth == 0 || !delta.is_finite() || (depth <= MAX_SIMPSON_DEPTH - MIN_SIMPSON_DEPTH && delta.abs() <= 15.0 * tolerance) {
+        return Some(left + right + delta / 15.0);
+    }
+    Some(simpson_step(f, (a, fa), (left_m, f_left_m), (m, fm), left, tolerance / 2.0, depth - 1)?
+        + simpson_step(f, (m, fm), (right_m, f_right_m), (b, fb), right, tolerance / 2.0, depth - 1)?)
+}
+
+///diff(f, x): the derivative of f at x, with central differences and Richardson extrapolation.
+///As there are no combined units, the result has the unit of f.
+fn diff(global_function_def: &GlobalFunctionDef, _scope: &Rc<RefCell<Scope>>, args: &Vec<Value>, range: &Range, errors: &mut Vec<Error>, globals: &Globals) -> Value {
+    if !match_arg_function(global_function_def, &args[0], range, errors) { return Value::error(range.clone()); }
+    let Some(number) = match_arg_number(global_function_def, &args[1], range, errors) else { return Value::error(range.clone()); };
+    let x = number.to_double();
+    let mut f_unit = None;
+    let mut f = |x| call_with_number(global_function_def, &args[0], number_with_unit(x, &number.unit), &mut f_unit, range, errors, globals);
+    let mut central_difference = |h: f64| Some((f(x + h)? - f(x - h)?) / (2.0 * h));
+    let h = 1e-3 * x.abs().max(1.0);
+    let Some(d1) = central_difference(h) else { return Value::error(range.clone()); };
+    let Some(d2) = central_difference(h / 2.0) else { return Value::error(range.clone()); };
+    let derivative = (4.0 * d2 - d1) / 3.0;
+    finite_number_or_error(global_function_def, derivative, f_unit.unwrap_or(Unit::none()), range, errors)
+}
+
+///Calls a function of a single number. The unit of the first result is kept in f_unit, and the next results are converted to it.
+fn call_with_number(function_def: &dyn FunctionDef, function_ref: &Value, number: Number, f_unit: &mut Option<Unit>, range: &Range, errors: &mut Vec<Error>, globals: &Globals) -> Option<f64> {
+    let real_error_count = count_real_errors(errors.iter());
+    let result = call_function_ref(function_ref, &vec![Value::from_number(number, range.clone())], range, errors, globals);
+    //stop at the first errors, instead of repeating them for every x.
+    if result.has_errors || real_error_count != count_real_errors(errors.iter()) {
+        return None;
+    }
+    let Some(number) = result.as_number() else {
+        errors.push(errors::func_arg_wrong_type(function_def.get_name(), "The function should return a number.", range.clone()));
+        return None;
+    };
+    let mut value = number.to_double();
+    let unit = f_unit.get_or_insert_with(|| number.unit.clone());
+    if let (Some(unit_def), Some(number_unit_def)) = (globals.unit_defs.get(&unit.id), globals.unit_defs.get(&number.unit.id)) {
+        if unit_def.property != number_unit_def.property {
+            errors.push(errors::unit_prop_diff(range.clone()));
+            return None;
+        }
+        value = unit_def.convert_from_si(number_unit_def.convert_to_si(value));
+    }
+    if !value.is_finite() {
+        errors.push(errors::not_finite(function_def.get_name(), range.clone()));
+        return None;
+    }
+    Some(value)
+}
+
+fn finite_number_or_error(function_def: &dyn FunctionDef, value: f64, unit: Unit, range: &Range, errors: &mut Vec<Error>) -> Value {
+    if !value.is_finite() {
+        return add_error_value(errors, errors::not_finite(function_def.get_name(), range.clone()));
+    }
+    Value::from_number(number_with_unit(value, &unit), range.clone())
+}
+
+fn number_with_unit(value: f64, unit: &Unit) -> Number {
+    let mut number = Number::from(value);
+    number.unit = unit.clone();
+    number
+}
+
+fn re(global_function_def: &GlobalFunctionDef, _scope: &Rc<RefCell<Scope>>, args: &Vec<Value>, range: &Range, errors: &mut Vec<Error>, _globals: &Globals) -> Value {
+    let Some(complex) = match_arg_complex(global_function_def, &args[0], range, errors) else { return Value::error(range.clone()); };
+    Value::from_number(Number::from(complex.re), range.clone())
+}
+
+fn im(global_function_def: &GlobalFunctionDef, _scope: &Rc<RefCell<Scope>>, args: &Vec<Value>, range: &Range, errors: &mut Vec<Error>, _globals: &Globals) -> Value {
+    let Some(complex) = match_arg_complex(global_function_def, &args[0], range, errors) else { return Value::error(range.clone()); };
+    Value::from_number(Number::from(complex.im), range.clone())
+}
+
+fn arg(global_function_def: &GlobalFunctionDef, _scope: &Rc<RefCell<Scope>>, args: &Vec<Value>, range: &Range, errors: &mut Vec<Error>, _globals: &Globals) -> Value {
+    let Some(complex) = match_arg_complex(global_function_def, &args[0], range, errors) else { return Value::error(range.clone()); };
+    Value::from_number(Number {significand: complex.arg(), exponent: 0, unit: Unit::from_id("rad", None), fmt: NumberFormat::Dec }, range.clone())
+}
+
+fn conj(global_function_def: &GlobalFunctionDef, _scope: &Rc<RefCell<Scope>>, args: &Vec<Value>, range: &Range, errors: &mut Vec<Error>, _globals: &Globals) -> Value {
+    let Some(complex) = match_arg_complex(global_function_def, &args[0], range, errors) else { return Value::error(range.clone()); };
+    Value::from_complex(complex.conj(), range.clone())
+}
+
+///A number is a complex number without imaginary part. Its unit is ignored.
+fn match_arg_complex(function_def: &dyn FunctionDef, arg: &Value, range: &Range, errors: &mut Vec<Error>) -> Option<Complex> {
+    match &arg.variant {
+        Variant::Complex { complex } => Some(complex.clone()),
+        Variant::Numeric { number } => Some(Complex::new(number.to_double(), 0.0, 'i')),
+        _ => {
+            errors.push(errors::func_arg_wrong_type(function_def.get_name(), "Expected a number or a complex number.", range.clone()));
+            None
+        }
+    }
+}
+
+fn match_arg_matrix<'a>(function_def: &dyn FunctionDef, arg: &'a Value, range: &Range, errors: &mut Vec<Error>) -> Option<(&'a Matrix, &'a Unit)> {
+    let Variant::Matrix { matrix, unit } = &arg.variant else {
+        errors.push(errors::func_arg_wrong_type(function_def.get_name(), "Expected a matrix.", range.clone()));
+        return None;
+    };
+    Some((matrix, unit))
+}
+
+fn match_arg_square_matrix<'a>(function_def: &dyn FunctionDef, arg: &'a Value, range: &Range, errors: &mut Vec<Error>) -> Option<&'a Matrix> {
+    let (matrix, _) = match_arg_matrix(function_def, arg, range, errors)?;
+    if !matrix.is_square() {
+        errors.push(errors::matrix_not_square(&matrix.size_text(), range.clone()));
+        return None;
+    }
+    Some(matrix)
+}
+
+///A list of numbers as SI values, with the SI unit of the first element.
+fn to_vector(function_def: &dyn FunctionDef, arg: &Value, range: &Range, errors: &mut Vec<Error>, globals: &Globals) -> Result<(Vec<f64>, Unit), Value> {
+    let Variant::List { values } = &arg.variant else {
+        return Err(add_error_value(errors, errors::func_arg_wrong_type(function_def.get_name(), "Expected a list of numbers.", range.clone())));
+    };
+    if values.iter().any(|value| !matches!(value.variant, Variant::Numeric {..})) {
+        return Err(add_error_value(errors, errors::func_arg_wrong_type(function_def.get_name(), "Expected a list of numbers.", range.clone())));
+    }
+    let args = vec![arg.clone()];
+    let vector = to_num_iter(function_def.get_name(), &args, range, errors, globals)?.collect();
+    //the units are checked by to_num_iter.
+    let unit = values.first()
+        .and_then(|value| value.as_number())
+        .and_then(|number| globals.unit_defs.get(&number.unit.id))
+        .map_or(Unit::none(), |unit_def| Unit::from_id(unit_def.si_id, None));
+    Ok((vector, unit))
+}
+
 fn with_num_vec_or_error_value(function_def: &dyn FunctionDef, args: &Vec<Value>, range: &Range, errors: &mut Vec<Error>, globals: &Globals, func: impl Fn(Vec<f64>) -> f64) -> Value {
     with_num_vec(function_def, args, range, errors, globals, func).unwrap_or_else(|err_value| err_value)
 }
@@ -553,14 +1129,20 @@ fn to_num_iter<'a>(function_name: &'a str, args: &'a Vec<Value>, _range: &'a Ran
     let Some(number) = arg.as_number() else {
         return Err(add_error_value(errors, errors::func_arg_wrong_type(function_name, "They must be numeric.", arg.stmt_range.clone())));
     };
-    let property = &globals.unit_defs[&number.unit.id].property;
+    let Some(unit_def) = globals.unit_defs.get(&number.unit.id) else {
+        return Err(add_error_value(errors, errors::unit_not_def(&number.unit.id, arg.stmt_range.clone())));
+    };
+    let property = &unit_def.property;
 
     //check rest of args
     for arg in iter {
         let Some(number) = arg.as_number() else {
             return Err(add_error_value(errors, errors::func_arg_wrong_type(function_name, "They must be numeric.", arg.stmt_range.clone())));
         };
-        if property != &globals.unit_defs[&number.unit.id].property {
+        let Some(unit_def) = globals.unit_defs.get(&number.unit.id) else {
+            return Err(add_error_value(errors, errors::unit_not_def(&number.unit.id, arg.stmt_range.clone())));
+        };
+        if property != &unit_def.property {
             return Err(add_error_value(errors, errors::unit_prop_diff(arg.stmt_range.clone())));
         }
     }
@@ -1,3 +1,4 @@
+use std::cell::Cell;
 use std::collections::HashMap;
 use crate::errors::Error;
 use crate::functions::{create_global_function_defs, FunctionView, GlobalFunctionDef};
@@ -11,6 +12,15 @@ use crate::tokenizer::cursor::Range;
 
 pub mod sources;
 
+fn constant(significand: f64, exponent: i32, unit: &str) -> Number {
+    Number {
+        significand,
+        exponent,
+        unit: Unit::from_id(unit, None),
+        fmt: NumberFormat::Dec,
+    }
+}
+
 ///Opaque wrapper to avoid any int value being used.
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub struct SourceIndex(u8);
@@ -28,8 +38,10 @@ pub struct Globals {
     pub unit_defs: HashMap<String, UnitDef>,
     pub global_function_defs:  HashMap<String, GlobalFunctionDef>,
     pub constants: HashMap<&'static str, Number>,
+    pub physics_constants: HashMap<&'static str, Number>, //only with `#define physics`
     pub units_view: UnitsView,
     pub function_view: FunctionView,
+    pub call_depth: Cell<usize>, //number of active custom function calls, to limit (mutual) recursion.
 }
 
 impl<'a> Globals {
@@ -38,7 +50,8 @@ impl<'a> Globals {
 
         let global_function_defs = create_global_function_defs();
         let constants = HashMap::new();
-        let mut globals = Globals { operators: HashMap::new(), sources: Vec::new(), unit_defs, global_function_defs, constants, units_view: UnitsView::new(), function_view: FunctionView::new() };
+        let physics_constants = HashMap::new();
+        let mut globals = Globals { operators: HashMap::new(), sources: Vec::new(), unit_defs, global_function_defs, constants, physics_constants, units_view: UnitsView::new(), function_view: FunctionView::new(), call_depth: Cell::new(0) };
         globals.units_view.add_default_classes(&globals.unit_defs);
         globals.units_view.remove_tagged(UnitTag::ShortDateTime, &globals.unit_defs);
         load_operators(&mut globals);
@@ -63,6 +76,10 @@ impl<'a> Globals {
             .find(|source| source.name == name)
     }
 
+    pub fn get_source(&self, source_index: SourceIndex) -> &Source {
+        &self.sources[source_index.0 as usize]
+    }
+
     pub fn get_operator(&self, op_id: u32) -> Option<&fn(&Globals, &Vec<Value>, &Range, &mut Vec<Error>)-> Value> {
         self.operators.get(&op_id)
     }
@@ -75,23 +92,42 @@ impl<'a> Globals {
         &self.sources[range.source_index.0 as usize].get_text()[range.start..range.end]
     }
 
+    ///Extends the range over the closing parentheses that it leaves open, as for the argument of an implicit mult in `f(x)`.
+    pub fn close_parentheses(&self, range: &Range) -> Range {
+        let text = self.sources[range.source_index.0 as usize].get_text();
+        let inner = &text[range.start..range.end];
+        let open = inner.matches('(').count().saturating_sub(inner.matches(')').count());
+        let closing = text[range.end..].chars().take(open).take_while(|c| *c == ')').count();
+        Range { end: range.end + closing, ..range.clone() }
+    }
+
     pub fn get_line_and_column(&self, range: &Range) -> (usize, usize) {
         self.sources[range.source_index.0 as usize].get_line_and_column(range.start)
     }
 
     pub fn get_lines_and_columns(&self, range: &Range) -> (usize, usize, usize, usize) {
-        let (l1, c1) = self.sources[range.source_index.0 as usize].get_line_and_column(range.start);
-        let (l2, c2) = self.sources[range.source_index.0 as usize].get_line_and_column(range.end);
+        let Some(source) = self.sources.get(range.source_index.0 as usize) else {
+            return (0, 0, 0, 0); //e.g. a source that was not found.
+        };
+        let (l1, c1) = source.get_line_and_column(range.start);
+        let (l2, c2) = source.get_line_and_column(range.end);
         (l1, c1, l2, c2)
     }
 
     fn fill_constants(&mut self) {
-        self.constants.insert("PI",  Number {
-            significand: std::f64::consts::PI,
-            exponent: 0,
-            unit: Unit::none(),
-            fmt: NumberFormat::Dec,
-        });
+        self.constants.insert("PI", constant(std::f64::consts::PI, 0, ""));
+
+        //short names that are often used as variables, so only with `#define physics`.
+        self.physics_constants.insert("e", constant(std::f64::consts::E, 0, ""));
+        self.physics_constants.insert("tau", constant(std::f64::consts::TAU, 0, ""));
+        self.physics_constants.insert("phi", constant(1.618033988749895, 0, ""));
+
+        //CODATA 2018, in SI units.
+        self.physics_constants.insert("c", constant(2.99792458, 8, "m/s"));
+        self.physics_constants.insert("G", constant(6.67430, -11, "m3/(kg*s2)"));
+        self.physics_constants.insert("h", constant(6.62607015, -34, "J*s"));
+        self.physics_constants.insert("k_B", constant(1.380649, -23, "J/K"));
+        self.physics_constants.insert("N_A", constant(6.02214076, 23, "/mol"));
     }
 
     pub fn is_unit(&self, unit: &Unit, property: UnitProperty) -> bool {
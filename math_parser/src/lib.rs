@@ -1,5 +1,6 @@
 use std::cell::RefCell;
 use crate::errors::Error;
+use crate::globals::SourceIndex;
 use crate::parser::{Parser};
 use crate::parser::nodes::CodeBlock;
 #[cfg(feature="print_nodes")]
@@ -18,6 +19,9 @@ mod functions;
 pub mod globals;
 mod date;
 mod number;
+mod matrix;
+mod complex;
+mod expression;
 pub mod number_format;
 
 pub struct Api {
@@ -52,7 +56,7 @@ impl Api {
             print_nodes(&stmt.node, 0, &self.globals);
         }
 
-        errors.append(&mut code_block.get_parser_errors().clone());
+        //the parser errors are already in `errors`: the code block only keeps a copy.
 
         //resolve
         let mut resolver = Resolver {
@@ -61,11 +65,15 @@ impl Api {
             errors: &mut errors,
             globals: &self.globals,
             muted: false,
-            current_statement_muted: false
+            current_statement_muted: false,
+            last_result: None,
+            echo_results: Vec::new(),
+            line_results: Vec::new(),
         };
         resolver.resolve(&code_block.statements);
 
-        resolver.results.sort_by(|v1, v2| v1.stmt_range.start.cmp(&v2.stmt_range.start)); //fast if list is nearly sorted, which it is.
+        //fast if list is nearly sorted, which it is. The start script comes before the main script.
+        resolver.results.sort_by_key(|value| (value.stmt_range.source_index.as_int(), value.stmt_range.start));
 
         serde_json::to_string_pretty(&resolver).unwrap() //unwrap: should always be possible to create a json string.
     }
@@ -73,7 +81,11 @@ impl Api {
     ///parse a file with either a given block or scope.
     fn parse_file(&mut self, script_id: &str, mut errors: &mut Vec<Error>, block_or_scope: Either) -> CodeBlock {
         let Some(source) = self.globals.get_source_by_name(&script_id) else {
-            panic!("TODO: return source file {} not found as an error -> json", script_id);
+            errors.push(errors::source_not_found(script_id, Range::none(SourceIndex::none())));
+            return match block_or_scope {
+                Either::Scope(scope) => CodeBlock::new(scope, Range::none(SourceIndex::none())),
+                Either::CodeBlock(block) => block,
+            };
         };
 
         let range = Range::none(source.index);
@@ -170,6 +182,21 @@ pub mod test_api {
         (value.clone(), number.clone())
     }
 
+    pub fn test_list(text: &str, expected_results: &[f64], unit: &str) {
+        let (results, _errors) = get_results(text);
+        let value = results.last().expect("No result found.");
+        let Variant::List { values, .. } = &value.variant else {
+            panic!("Result isn't a list.");
+        };
+        let precision = 10000000.0;
+        let numbers: Vec<f64> = values.iter()
+            .map(|value| value.as_number().expect("List item isn't a number."))
+            .inspect(|number| assert_eq!(number.unit.id, unit))
+            .map(|number| (number.to_double() * precision).round() / precision)
+            .collect();
+        assert_eq!(numbers, expected_results, "statement \"{}\"", text);
+    }
+
     pub fn test_date(text: &str, day: i8, month: i32, year: Option<i32>) {
         let (results, _errors) = get_results(text);
         let value = results.last().expect("No result found.");
@@ -192,6 +219,43 @@ pub mod test_api {
         assert_eq!(duration.years, years);
     }
 
+    pub fn test_bool(text: &str, expected_result: bool) {
+        let (results, _errors) = get_results(text);
+        let value = results.last().expect("No result found.");
+        let Variant::Bool { value, .. } = &value.variant else {
+            panic!("Result isn't a bool.");
+        };
+        assert_eq!(*value, expected_result, "statement \"{}\"", text);
+    }
+
+    pub fn test_text(text: &str, expected_result: &str) {
+        let (results, _errors) = get_results(text);
+        let value = results.last().expect("No result found.");
+        let Variant::Text { text: result, .. } = &value.variant else {
+            panic!("Result isn't a text.");
+        };
+        assert_eq!(result, expected_result, "statement \"{}\"", text);
+    }
+
+    pub fn test_echo(text: &str, expected_result: &str) {
+        let (results, _errors) = get_results(text);
+        let value = results.last().expect("No result found.");
+        let Variant::Comment { text: result } = &value.variant else {
+            panic!("Result isn't an echo comment.");
+        };
+        assert_eq!(result, expected_result, "statement \"{}\"", text);
+    }
+
+    ///Checks the echoed source text of the last result.
+    pub fn test_echoed_code(text: &str, expected_code: &str) {
+        let (results, _errors) = get_results(text);
+        let value = results.last().expect("No result found.");
+        let Some(echo) = &value.echo else {
+            panic!("Result isn't echoed.");
+        };
+        assert_eq!(&text[echo.start..echo.end], expected_code, "statement \"{}\"", text); //the text is the only source.
+    }
+
     pub fn test_error(text: &str, error_id: ErrorId) {
         let (_results, errors) = get_results(text);
         assert_ne!(errors.iter().filter(|&e| e.id == error_id).count(), 0, "statement \"{}\" did not report error {:?}", text, error_id);
@@ -202,10 +266,33 @@ pub mod test_api {
         assert_eq!(errors.len(), 0);
     }
 
+    pub fn test_include_result(includes: &[(&str, &str)], text: &str, expected_result: f64, unit: &str) {
+        let (results, _errors) = get_results_with_includes(text, includes);
+        let value = results.last().expect("No result found.");
+        let number = value.as_number().expect("Result isn't a number.");
+        assert_eq!((number.to_double() * 10000000.0).round() / 10000000.0, expected_result, "statement \"{}\"", text);
+        assert_eq!(number.unit.id, unit);
+    }
+
+    ///Checks that the error is reported in the given source.
+    pub fn test_include_error(includes: &[(&str, &str)], text: &str, error_id: ErrorId, source_name: &str) {
+        let (_results, errors) = get_results_with_includes(text, includes);
+        let source_index = if source_name == "src1" { 0 } else { 1 + includes.iter().position(|(name, _)| *name == source_name).expect("Unknown source name.") };
+        assert_ne!(errors.iter().filter(|&e| e.id == error_id && e.range.source_index.as_int() == source_index as i32).count(), 0, "statement \"{}\" did not report error {:?} in source {}", text, error_id, source_name);
+    }
+
     pub fn get_results(text: &str) -> (Vec<Value>, Vec<Error>) {
+        get_results_with_includes(text, &[])
+    }
+
+    ///The text is the main source `src1`. The other sources can be included by name.
+    pub fn get_results_with_includes(text: &str, includes: &[(&str, &str)]) -> (Vec<Value>, Vec<Error>) {
         let mut globals = Globals::new();
         let src_name = "src1";
         let source_index = globals.set_source(src_name.to_string(), text.to_string());
+        for (name, include_text) in includes {
+            globals.set_source(name.to_string(), include_text.to_string());
+        }
         let mut tok = PeekingTokenizer::new(globals.get_source_by_name(src_name).unwrap()); //unwrap ok: we just pushed a source.
         let scope = Scope::new(&globals);
         let code_block = CodeBlock::new(RefCell::new(scope), Range::none(source_index));
@@ -214,7 +301,6 @@ pub mod test_api {
         let mut parser = Parser::new(&globals, &mut tok, &mut errors, code_block);
         parser.parse(false, false);
         let code_block: CodeBlock = parser.into();
-        errors.append(&mut code_block.get_parser_errors().clone());
         //resolve
         let mut resolver = Resolver {
             scope: code_block.scope.clone(),
@@ -222,7 +308,10 @@ pub mod test_api {
             errors: &mut errors,
             globals: &globals,
             muted: false,
-            current_statement_muted: false
+            current_statement_muted: false,
+            last_result: None,
+            echo_results: Vec::new(),
+            line_results: Vec::new(),
         };
         resolver.resolve(&code_block.statements);
         (resolver.results, errors)
@@ -0,0 +1,162 @@
+///A matrix of plain numbers, stored row by row.
+#[derive(Clone)]
+pub struct Matrix {
+    pub rows: usize,
+    pub cols: usize,
+    values: Vec<f64>,
+}
+
+impl Matrix {
+    pub fn new(rows: usize, cols: usize, values: Vec<f64>) -> Self {
+        assert_eq!(rows * cols, values.len());
+        Matrix { rows, cols, values }
+    }
+
+    pub fn identity(size: usize) -> Self {
+        let mut matrix = Matrix::new(size, size, vec![0.0; size * size]);
+        (0..size).for_each(|i| matrix.set(i, i, 1.0));
+        matrix
+    }
+
+    #[inline]
+    pub fn get(&self, row: usize, col: usize) -> f64 {
+        self.values[row * self.cols + col]
+    }
+
+    #[inline]
+    fn set(&mut self, row: usize, col: usize, value: f64) {
+        self.values[row * self.cols + col] = value;
+    }
+
+    pub fn row(&self, row: usize) -> &[f64] {
+        &self.values[row * self.cols..(row + 1) * self.cols]
+    }
+
+    pub fn is_square(&self) -> bool {
+        self.rows == self.cols
+    }
+
+    ///E.g. `2x3` for 2 rows and 3 columns.
+    pub fn size_text(&self) -> String {
+        format!("{}x{}", self.rows, self.cols)
+    }
+
+    pub fn map(&self, f: impl Fn(f64) -> f64) -> Matrix {
+        Matrix::new(self.rows, self.cols, self.values.iter().map(|v| f(*v)).collect())
+    }
+
+    ///Element-wise. None if the sizes differ.
+    pub fn zip(&self, other: &Matrix, f: impl Fn(f64, f64) -> f64) -> Option<Matrix> {
+        if self.rows != other.rows || self.cols != other.cols {
+            return None;
+        }
+        Some(Matrix::new(self.rows, self.cols, self.values.iter().zip(other.values.iter()).map(|(v1, v2)| f(*v1, *v2)).collect()))
+    }
+
+    pub fn transpose(&self) -> Matrix {
+        let mut transposed = Matrix::new(self.cols, self.rows, vec![0.0; self.values.len()]);
+        for row in 0..self.rows {
+            for col in 0..self.cols {
+                transposed.set(col, row, self.get(row, col));
+            }
+        }
+        transposed
+    }
+
+    ///None if the number of columns differs from the number of rows of the other matrix.
+    pub fn mul(&self, other: &Matrix) -> Option<Matrix> {
+        if self.cols != other.rows {
+            return None;
+        }
+        let mut product = Matrix::new(self.rows, other.cols, vec![0.0; self.rows * other.cols]);
+        for row in 0..self.rows {
+            for col in 0..other.cols {
+                product.set(row, col, (0..self.cols).map(|i| self.get(row, i) * other.get(i, col)).sum());
+            }
+        }
+        Some(product)
+    }
+
+    ///Assumes a square matrix.
+    pub fn det(&self) -> f64 {
+        let mut lu = self.clone();
+        let mut det = 1.0;
+        for col in 0..self.cols {
+            let Some(pivot_row) = lu.pivot_row(col) else {
+                return 0.0;
+            };
+            if pivot_row != col {
+                lu.swap_rows(pivot_row, col);
+                det = -det;
+            }
+            det *= lu.get(col, col);
+            lu.eliminate_below(col);
+        }
+        det
+    }
+
+    ///Assumes a square matrix. None if the matrix is singular.
+    pub fn inverse(&self) -> Option<Matrix> {
+        self.solve_for(Matrix::identity(self.rows))
+    }
+
+    ///Solves `self * x = b`. Assumes a square matrix. None if the matrix is singular.
+    pub fn solve(&self, b: &[f64]) -> Option<Vec<f64>> {
+        self.solve_for(Matrix::new(b.len(), 1, b.to_vec())).map(|x| x.values)
+    }
+
+    ///Gauss-Jordan elimination with partial pivoting, applied to both self and the right hand side.
+    fn solve_for(&self, mut rhs: Matrix) -> Option<Matrix> {
+        let mut a = self.clone();
+        for col in 0..a.cols {
+            let pivot_row = a.pivot_row(col)?;
+            a.swap_rows(pivot_row, col);
+            rhs.swap_rows(pivot_row, col);
+            let pivot = a.get(col, col);
+            for row in 0..a.rows {
+                if row == col {
+                    continue;
+                }
+                let factor = a.get(row, col) / pivot;
+                a.subtract_row(row, col, factor);
+                rhs.subtract_row(row, col, factor);
+            }
+        }
+        for row in 0..a.rows {
+            let pivot = a.get(row, row);
+            for col in 0..rhs.cols {
+                rhs.set(row, col, rhs.get(row, col) / pivot);
+            }
+        }
+        Some(rhs)
+    }
+
+    ///The row, from `col` down, with the largest value in that column. None if all are (near) zero.
+    fn pivot_row(&self, col: usize) -> Option<usize> {
+        let scale = self.values.iter().fold(0.0_f64, |max, v| max.max(v.abs()));
+        (col..self.rows)
+            .max_by(|r1, r2| self.get(*r1, col).abs().total_cmp(&self.get(*r2, col).abs()))
+            .filter(|row| self.get(*row, col).abs() > scale * 1e-12)
+    }
+
+    fn swap_rows(&mut self, row1: usize, row2: usize) {
+        for col in 0..self.cols {
+            self.values.swap(row1 * self.cols + col, row2 * self.cols + col);
+        }
+    }
+
+    ///row = row - factor * source_row
+    fn subtract_row(&mut self, row: usize, source_row: usize, factor: f64) {
+        for col in 0..self.cols {
+            self.set(row, col, self.get(row, col) - factor * self.get(source_row, col));
+        }
+    }
+
+    fn eliminate_below(&mut self, col: usize) {
+        let pivot = self.get(col, col);
+        for row in col + 1..self.rows {
+            let factor = self.get(row, col) / pivot;
+            self.subtract_row(row, col, factor);
+        }
+    }
+}
@@ -1,8 +1,10 @@
 use crate::errors;
 use crate::errors::{Error, unknown_expr};
-use crate::parser::nodes::{AssignExpr, AssignableExpr, BinExpr, CallExpr, CodeBlock, CommentExpr, ConstExpr, ConstType, Define, DefineExpr, DefineType, FunctionDefExpr, HasRange, IdExpr, ListExpr, Node, NodeType, NoneExpr, PostfixExpr, Pragma, PragmaExpr, PragmaType, Statement, UnaryExpr, UnitExpr};
+use crate::parser::nodes::{ApproxExpr, AssignExpr, BinExpr, CallExpr, CodeBlock, CommentExpr, ConstDefExpr, ConstExpr, EchoPart, ConstType, Define, DefineExpr, DefineType, FunctionDefExpr, HasRange, IdExpr, IfExpr, IncludeExpr, MatrixExpr, RecordExpr, RecordField, IndexExpr, IndexType, LambdaExpr, ListExpr, Node, NodeType, NoneExpr, PostfixExpr, Pragma, PragmaExpr, PragmaType, RangeExpr, ResultRefExpr, ResultRefType, Selector, SelectorType, Statement, TernaryExpr, UnaryExpr, UnitExpr};
 use crate::parser::nodes::DefineType::Precision;
-use crate::globals::Globals;
+use crate::globals::{Globals, SourceIndex};
+use crate::globals::sources::Source;
+use crate::number::Number;
 use crate::tokenizer::cursor::Range;
 use crate::tokenizer::peeking_tokenizer::PeekingTokenizer;
 use crate::tokenizer::token_type::TokenType;
@@ -18,7 +20,11 @@ pub struct Parser<'g, 'a, 't> {
     tok: &'a mut PeekingTokenizer<'t>,
     errors: &'a mut Vec<Error>,
     code_block: CodeBlock,
-    mute_block: bool
+    mute_block: bool,
+    continuation: Option<Box<Node>>,
+    echo_template: bool,
+    echo_block: bool,
+    include_stack: Vec<SourceIndex>, //the sources being parsed, to detect cyclic includes.
 }
 
 impl<'g, 'a, 't> Into<CodeBlock> for Parser<'g, 'a, 't> {
@@ -30,20 +36,26 @@ impl<'g, 'a, 't> Into<CodeBlock> for Parser<'g, 'a, 't> {
 
 impl<'g, 'a, 't> Parser<'g, 'a, 't> {
     pub fn new (globals: &'g Globals, tok: &'a mut PeekingTokenizer<'t>, errors: &'a mut Vec<Error>, code_block: CodeBlock) -> Self {
+        let include_stack = vec![tok.source_index()];
         Parser {
             globals,
             tok,
             errors,
             code_block,
             mute_block: false,
+            continuation: None,
+            echo_template: false,
+            echo_block: false,
+            include_stack,
         }
     }
 
     pub fn parse(&mut self, for_block: bool, inherited_mute: bool) {
         self.mute_block = inherited_mute;
         while self.tok.peek().kind != TokenType::Eot {
-            let stmt = self.parse_statement();
-            self.code_block.statements.push(stmt);
+            if let Some(stmt) = self.parse_statement() {
+                self.code_block.statements.push(stmt);
+            }
             if self.tok.peek().kind == TokenType::CurlClose {
                 if for_block {
                     self.errors.append(&mut self.tok.get_errors().clone());//TODO: is this clone needed?
@@ -57,7 +69,53 @@ impl<'g, 'a, 't> Parser<'g, 'a, 't> {
         self.errors.append(&mut self.tok.get_errors().clone());//TODO: is this clone needed?
     }
 
-    fn parse_statement(&mut self) -> Statement {
+    ///`!stmt;` echoes the code of the statement, `!!stmt;` and all statements between `!///` and `///!` echo the full line.
+    ///None if there's no statement after the echo marker.
+    fn parse_statement(&mut self) -> Option<Statement> {
+        loop {
+            if self.match_token(&TokenType::EchoStart) {
+                self.echo_block = true;
+            } else if self.match_token(&TokenType::EchoEnd) {
+                self.echo_block = false;
+            } else {
+                break;
+            }
+        }
+        if self.tok.peek().kind == TokenType::Eot {
+            return None;
+        }
+        let (echo_stmt, echo_line) = if self.match_token(&TokenType::Exclam) {
+            let echo_line = self.match_token(&TokenType::Exclam);
+            (!echo_line, echo_line)
+        } else {
+            (false, self.echo_block)
+        };
+        let mut stmt = self.parse_muted_statement();
+        if let NodeType::Comment(_) = stmt.node.expr {
+            return Some(stmt);
+        }
+        if echo_stmt {
+            stmt.echo = Some(stmt.get_range());
+        }
+        if echo_line {
+            stmt.echo = Some(self.get_echo_line(&stmt.get_range()));
+        }
+        Some(stmt)
+    }
+
+    ///From the start of the statement to the end of the line, or up to the next statement on the same line.
+    fn get_echo_line(&self, stmt_range: &Range) -> Range {
+        let text = self.globals.get_source(stmt_range.source_index).get_text();
+        let mut end = text[stmt_range.end..].find('\n').map_or(text.len(), |pos| stmt_range.end + pos);
+        let next = self.tok.peek();
+        if next.kind != TokenType::Eot && next.range.start >= stmt_range.end {
+            end = end.min(next.range.start);
+        }
+        end = stmt_range.start + text[stmt_range.start..end].trim_end().len();
+        Range { end, ..stmt_range.clone() }
+    }
+
+    fn parse_muted_statement(&mut self) -> Statement {
         let mut mute_line = false;
         if self.match_token(&TokenType::MuteLine) {
             mute_line = true;
@@ -72,13 +130,13 @@ impl<'g, 'a, 't> Parser<'g, 'a, 't> {
         }
         //TODO: all return statements first update the mute state of the stmt.
         // wrap it with another function (or block?) to get rid of all these set_mute() calls.
-        if self.tok.peek().kind == TokenType::CurlOpen {
+        if self.tok.peek().kind == TokenType::CurlOpen && !self.is_record_start() {
             let curl_open = self.tok.next();
-            let block = self.parse_block(curl_open.range.clone());
+            let block = self.parse_block(curl_open.range.clone(), false);
             if !self.match_token(&TokenType::CurlClose) {
                 self.errors.push(errors::expected("}", self.tok.peek().range.clone()));
             }
-            return Statement { node: Node::boxed(NodeType::Block(block)), mute: mute_line | self.mute_block };
+            return Statement { node: Node::boxed(NodeType::Block(block)), mute: mute_line | self.mute_block, echo: None };
         }
         if let Some(stmt) = self.parse_echo_comment() {
             return stmt.set_mute(mute_line | self.mute_block);
@@ -89,9 +147,18 @@ impl<'g, 'a, 't> Parser<'g, 'a, 't> {
         if let Some(stmt) = self.parse_defines() {
             return stmt.set_mute(mute_line | self.mute_block);
         }
+        if let Some(stmt) = self.parse_include() {
+            return stmt.set_mute(mute_line | self.mute_block);
+        }
         if let Some(stmt) = self.parse_function_def() {
             return stmt.set_mute(mute_line | self.mute_block);
         }
+        if let Some(stmt) = self.parse_const_def() {
+            return stmt.set_mute(mute_line | self.mute_block);
+        }
+        if let Some(stmt) = self.parse_if_statement() {
+            return stmt.set_mute(mute_line | self.mute_block);
+        }
         self.parse_expr_statement().set_mute(mute_line | self.mute_block)
     }
 
@@ -110,7 +177,7 @@ impl<'g, 'a, 't> Parser<'g, 'a, 't> {
                 }
             }
             self.tok.set_nl_is_token(false);
-            return Some(Statement { mute: false, node: Node::boxed(NodeType::Define(DefineExpr {
+            return Some(Statement { mute: false, echo: None, node: Node::boxed(NodeType::Define(DefineExpr {
                     def_undef: t,
                     defines,
                 }))
@@ -128,22 +195,15 @@ impl<'g, 'a, 't> Parser<'g, 'a, 't> {
             "dmy" => DefineType::Dmy,
             "mdy" => DefineType::Mdy,
             "precision" => {
-                let eq = self.tok.peek();
-                if eq.kind != TokenType::Eq {
-                    self.errors.push(errors::expected("=", eq.range.clone()));
-                    return None;
-                }
-                self.tok.next(); //eq
-                let int = self.tok.peek();
-                if int.kind != TokenType::Number {
-                    self.errors.push(errors::expected("an integer", int.range.clone()));
-                    return None;
-                }
-                let number_token = self.tok.next();
-                extra_range = Some(number_token.range);
-                let number = self.tok.get_number();
+                let (number, number_range) = self.parse_define_number()?;
+                extra_range = Some(number_range);
                 Precision {number}
             },
+            "recursion" => {
+                let (number, number_range) = self.parse_define_number()?;
+                extra_range = Some(number_range);
+                DefineType::Recursion {number}
+            },
             "date_units" => DefineType::DateUnits,
             "short_date_units" => DefineType::ShortDateUnits,
             "trig" => DefineType::Trig,
@@ -151,6 +211,7 @@ impl<'g, 'a, 't> Parser<'g, 'a, 't> {
             "date" => DefineType::Date,
             "all" => DefineType::Default,
             "electric" => DefineType::Electric,
+            "physics" => DefineType::Physics,
             "strict" => DefineType::Strict,
             "decimal_dot" | "dec_dot" | "dot" => DefineType::DecimalDot,
             "decimal_comma" | "dec_comma" | "comma" => DefineType::DecimalComma,
@@ -167,6 +228,54 @@ impl<'g, 'a, 't> Parser<'g, 'a, 't> {
         Some(Define{ define_type, range})
     }
 
+    ///Parses the `=number` part of a define option.
+    fn parse_define_number(&mut self) -> Option<(Number, Range)> {
+        let eq = self.tok.peek();
+        if eq.kind != TokenType::Eq {
+            self.errors.push(errors::expected("=", eq.range.clone()));
+            return None;
+        }
+        self.tok.next(); //eq
+        let int = self.tok.peek();
+        if int.kind != TokenType::Number {
+            self.errors.push(errors::expected("an integer", int.range.clone()));
+            return None;
+        }
+        let number_token = self.tok.next();
+        Some((self.tok.get_number(), number_token.range))
+    }
+
+    ///`#include "name"` parses the source with that name into the current scope.
+    fn parse_include(&mut self) -> Option<Statement> {
+        if self.tok.peek().kind != TokenType::Include {
+            return None;
+        }
+        let include_token = self.tok.next();
+        if self.tok.peek().kind != TokenType::DoubleQuotedStr {
+            return Some(Statement::error(&mut self.errors, errors::expected("\"source name\"", self.tok.peek().range.clone()), self.tok.peek().clone()));
+        }
+        let name_range = self.tok.next().range;
+        let name = self.globals.get_text(&name_range);
+        match self.globals.get_source_by_name(name) {
+            None => self.errors.push(errors::source_not_found(name, name_range.clone())),
+            Some(source) if self.include_stack.contains(&source.index) => self.errors.push(errors::include_cycle(name, name_range.clone())),
+            Some(source) => self.parse_included_source(source),
+        }
+        Some(Statement { mute: false, echo: None, node: Node::boxed(NodeType::Include(IncludeExpr { include_token, name_range })) })
+    }
+
+    ///The statements are added to the current code block, so that they share its scope.
+    fn parse_included_source(&mut self, source: &Source) {
+        let mut tok = PeekingTokenizer::new(source);
+        let code_block = CodeBlock::with_scope(self.code_block.scope.clone(), Range::none(source.index));
+        let mut parser = Parser::new(self.globals, &mut tok, self.errors, code_block);
+        parser.include_stack = self.include_stack.clone();
+        parser.include_stack.push(source.index);
+        parser.parse(false, true); //an included source is a library: only its definitions matter, not its results.
+        let mut statements = std::mem::take(&mut parser.code_block.statements);
+        self.code_block.statements.append(&mut statements);
+    }
+
     fn parse_pragmas_and_set(&mut self) -> Option<Statement> {
         if self.tok.peek().kind == TokenType::Pragma {
             let t = self.tok.next();
@@ -182,7 +291,7 @@ impl<'g, 'a, 't> Parser<'g, 'a, 't> {
                 }
             }
             self.tok.set_nl_is_token(false);
-            return Some(Statement { mute: false, node: Node::boxed(NodeType::Pragma(PragmaExpr {
+            return Some(Statement { mute: false, echo: None, node: Node::boxed(NodeType::Pragma(PragmaExpr {
                 pragma_token: t,
                 pragmas,
             }))
@@ -217,12 +326,79 @@ impl<'g, 'a, 't> Parser<'g, 'a, 't> {
         if token.kind != TokenType::EchoCommentLine {
             return None;
         };
+        let token = self.tok.next();
+        let parts = self.parse_echo_parts(&token.range);
         Some( Statement {
-            node: Node::boxed(NodeType::Comment(CommentExpr { token: self.tok.next() })),
+            node: Node::boxed(NodeType::Comment(CommentExpr { token, parts })),
             mute: false,
+            echo: None,
         })
     }
 
+    ///Splits an echo comment in text, `` `expr` ``, `` !`expr` `` and `$` or `$0` placeholders. `$$` is a literal `$`.
+    fn parse_echo_parts(&mut self, range: &Range) -> Vec<EchoPart> {
+        let text = self.globals.get_text(range).as_bytes();
+        let mut parts = Vec::new();
+        let mut text_start = 0;
+        let mut i = 0;
+        while i < text.len() {
+            let echo_code = text[i] == b'!' && text.get(i+1) == Some(&b'`');
+            if text[i] == b'`' || echo_code {
+                let expr_start = i + 1 + echo_code as usize;
+                let Some(len) = text[expr_start..].iter().position(|&c| c == b'`') else {
+                    self.errors.push(errors::expected("`", Range { start: range.end, ..range.clone() }));
+                    break;
+                };
+                let expr_range = Range { start: range.start + expr_start, end: range.start + expr_start + len, ..range.clone() };
+                parts.push(EchoPart::Text(Range { start: range.start + text_start, end: range.start + i, ..range.clone() }));
+                parts.push(EchoPart::Expr { node: self.parse_echo_expr(&expr_range), echo_code });
+                i = expr_start + len + 1;
+                text_start = i;
+            } else if text[i] == b'$' && text.get(i+1) == Some(&b'$') {
+                parts.push(EchoPart::Text(Range { start: range.start + text_start, end: range.start + i + 1, ..range.clone() }));
+                i += 2;
+                text_start = i;
+            } else if text[i] == b'$' {
+                let digits = text[i+1..].iter().take_while(|c| c.is_ascii_digit()).count();
+                let token_range = Range { start: range.start + i, end: range.start + i + 1 + digits, ..range.clone() };
+                let index = self.globals.get_text(&token_range)[1..].parse().ok(); //None for a single `$`
+                parts.push(EchoPart::Text(Range { start: range.start + text_start, end: range.start + i, ..range.clone() }));
+                parts.push(EchoPart::Result(ResultRefExpr {
+                    token: Token {
+                        kind: TokenType::LineRef,
+                        range: token_range,
+                        #[cfg(debug_assertions)]
+                        text: "".to_string(),
+                    },
+                    ref_type: ResultRefType::Echo(index),
+                    implicit: false,
+                }));
+                i += 1 + digits;
+                text_start = i;
+            } else {
+                i += 1;
+            }
+        }
+        parts.push(EchoPart::Text(Range { start: range.start + text_start, ..range.clone() }));
+        parts.retain(|part| !matches!(part, EchoPart::Text(text) if text.start == text.end));
+        parts
+    }
+
+    ///Parses the expression between backticks with its own tokenizer, but in the current scope.
+    fn parse_echo_expr(&mut self, range: &Range) -> Box<Node> {
+        let mut tok = PeekingTokenizer::for_range(self.globals.get_source(range.source_index), range);
+        let code_block = CodeBlock::with_scope(self.code_block.scope.clone(), range.clone());
+        let mut parser = Parser::new(self.globals, &mut tok, self.errors, code_block);
+        parser.echo_template = true;
+        let mut node = parser.parse_assign_expr();
+        if parser.tok.peek().kind != TokenType::Eot {
+            parser.errors.push(errors::expected("`", parser.tok.peek().range.clone()));
+            node.has_errors = true;
+        }
+        self.errors.append(&mut tok.get_errors().clone());
+        node
+    }
+
     fn parse_function_def(&mut self) -> Option<Statement> {
         if self.tok.peek().kind != TokenType::Function {
             return None;
@@ -238,10 +414,25 @@ impl<'g, 'a, 't> Parser<'g, 'a, 't> {
         };
 
         let mut param_defs: Vec<String> = Vec::new();
+        let mut default_values: Vec<Box<Node>> = Vec::new();
+        let mut variadic = false;
 
         while self.tok.peek().kind == TokenType::Id {
-            let txt = self.globals.get_text(&self.tok.next().range).to_string();
-            param_defs.push(txt);
+            let param = self.tok.next();
+            let txt = self.globals.get_text(&param.range).to_string();
+            param_defs.push(txt.clone());
+            if self.match_token(&TokenType::Ellipsis) {
+                if self.tok.peek().kind != TokenType::ParClose {
+                    return Some(Statement::error(&mut self.errors, errors::func_param_def(&txt, "A variadic parameter should be the last one.", param.range.clone()), param));
+                }
+                variadic = true;
+                break;
+            }
+            if self.match_token(&TokenType::Eq) {
+                default_values.push(self.parse_ternary_expr());
+            } else if !default_values.is_empty() {
+                return Some(Statement::error(&mut self.errors, errors::func_param_def(&txt, "A parameter without default value can't follow a parameter with a default value.", param.range.clone()), param));
+            }
             if self.match_token(&TokenType::Comma) {
                 continue;
             }
@@ -258,39 +449,126 @@ impl<'g, 'a, 't> Parser<'g, 'a, 't> {
             return Some(Statement::error(&mut self.errors, errors::expected("{", self.tok.peek().range.clone()), self.tok.peek().clone()));
         };
         let curl_open = self.tok.next();
-        let new_code_block = self.parse_block(curl_open.range.clone());
+        let name = self.globals.get_text(&id.range).to_string();
+        let mut fun_def_expr = FunctionDefExpr {
+            id: format!("{}#{}", name, param_defs.len()), //overloads differ in the number of params.
+            name,
+            id_range: id.range.clone(),
+            arg_names: param_defs,
+            variadic,
+            range: &start_range + &curl_open.range,
+        };
+        //add the function before parsing the body, to allow recursive calls.
+        let empty_code_block = CodeBlock::new(Scope::copy_for_block(&self.code_block.scope), curl_open.range.clone());
+        self.code_block.scope.borrow_mut().add_local_function(empty_code_block, &fun_def_expr, default_values);
+        let new_code_block = self.parse_block(curl_open.range.clone(), false);
+        let body_range = &start_range + &new_code_block.get_range();
+        self.code_block.scope.borrow_mut().set_local_function_body(&fun_def_expr.id, new_code_block, body_range);
 
         if self.tok.peek().kind != TokenType::CurlClose {
             return Some(Statement::error(&mut self.errors, errors::expected("}", self.tok.peek().range.clone()), self.tok.peek().clone()));
         };
         let token_end = self.tok.next();
-        let fun_def_expr = FunctionDefExpr {
-            id: self.globals.get_text(&id.range).to_string(),
-            id_range: id.range.clone(),
-            arg_names: param_defs,
-            range: &start_range + &token_end.range,
-        };
-        self.code_block.scope.borrow_mut().add_local_function(new_code_block, &fun_def_expr);
+        fun_def_expr.range = &start_range + &token_end.range;
         let node = Node::new(NodeType::FunctionDef(fun_def_expr));
         Some(Statement {
             node: Box::new(node),
             mute: false,
+            echo: None,
         })
     }
 
-    fn parse_block(&mut self, block_start: Range) -> CodeBlock {
+    fn parse_const_def(&mut self) -> Option<Statement> {
+        if self.tok.peek().kind != TokenType::Const {
+            return None;
+        };
+        let const_token = self.tok.next();
+        let mut stmt = self.parse_expr_statement();
+        let is_variable_assign = match &stmt.node.expr {
+            NodeType::Assign(assign_expr) => assign_expr.op.kind == TokenType::Eq
+                && matches!(&assign_expr.assignable.expr, NodeType::Id(IdExpr { selector: None, .. })),
+            _ => false
+        };
+        if !is_variable_assign {
+            self.errors.push(errors::expected("name = value", stmt.get_range()));
+            stmt.node.has_errors = true;
+            return Some(stmt);
+        }
+        stmt.node = Node::boxed(NodeType::ConstDef(ConstDefExpr { const_token, assign: stmt.node }));
+        Some(stmt)
+    }
+
+    fn parse_if_statement(&mut self) -> Option<Statement> {
+        if self.tok.peek().kind != TokenType::If {
+            return None;
+        };
+        let node = self.parse_if_expr().unwrap_or_else(|stmt| stmt.node);
+        Some(Statement {
+            node,
+            mute: false,
+            echo: None,
+        })
+    }
+
+    fn parse_if_expr(&mut self) -> Result<Box<Node>, Statement> {
+        let if_token = self.tok.next();
+        if !self.match_token(&TokenType::ParOpen) {
+            return Err(Statement::error(&mut self.errors, errors::expected("(", self.tok.peek().range.clone()), self.tok.peek().clone()));
+        };
+        let condition = self.parse_ternary_expr();
+        if !self.match_token(&TokenType::ParClose) {
+            return Err(Statement::error(&mut self.errors, errors::expected(")", self.tok.peek().range.clone()), self.tok.peek().clone()));
+        };
+        let then_block = self.parse_curl_block()?;
+        let else_node = if self.match_token(&TokenType::Else) {
+            if self.tok.peek().kind == TokenType::If {
+                Some(self.parse_if_expr()?)
+            } else {
+                Some(Node::boxed(NodeType::Block(self.parse_curl_block()?)))
+            }
+        } else {
+            None
+        };
+        Ok(Node::boxed(NodeType::If(IfExpr { if_token, condition, then_block, else_node })))
+    }
+
+    /// A block that is part of a statement, like a branch of an if-statement.
+    fn parse_curl_block(&mut self) -> Result<CodeBlock, Statement> {
+        if self.tok.peek().kind != TokenType::CurlOpen {
+            return Err(Statement::error(&mut self.errors, errors::expected("{", self.tok.peek().range.clone()), self.tok.peek().clone()));
+        };
+        let curl_open = self.tok.next();
+        let code_block = self.parse_block(curl_open.range.clone(), true);
+        if !self.match_token(&TokenType::CurlClose) {
+            return Err(Statement::error(&mut self.errors, errors::expected("}", self.tok.peek().range.clone()), self.tok.peek().clone()));
+        };
+        Ok(code_block)
+    }
+
+    fn parse_block(&mut self, block_start: Range, inherit_variables: bool) -> CodeBlock {
         let new_scope = Scope::copy_for_block(&self.code_block.scope);
+        if inherit_variables {
+            let mut scope = new_scope.borrow_mut();
+            scope.inherit_variables = true;
+            scope.var_defs = self.code_block.scope.borrow().var_defs.clone();
+            scope.function_ref_defs = self.code_block.scope.borrow().function_ref_defs.clone();
+        }
         let new_code_block = CodeBlock::new(new_scope, block_start);
         let mut parser = Parser::new(&self.globals, &mut self.tok, &mut self.errors, new_code_block);
+        parser.include_stack = self.include_stack.clone();
+        parser.echo_block = self.echo_block;
         parser.parse(true, self.mute_block);
         parser.into()
     }
 
     fn parse_expr_statement(&mut self) -> Statement {
+        self.parse_continuation();
         let mut stmt = Statement {
             node: self.parse_assign_expr(),
             mute: false,
+            echo: None,
         };
+        self.continuation = None;
         match self.tok.peek().kind {
             TokenType::SemiColon => {
                 self.tok.next();
@@ -306,60 +584,58 @@ impl<'g, 'a, 't> Parser<'g, 'a, 't> {
         stmt
     }
 
-    fn parse_assign_expr(&mut self) -> Box<Node> {
-        let assignable = self.parse_assignable(); //TODO: should only eat assignable. For now, just an ID. We'll deal with fragments later.
-        let Some(assignable) = assignable else {
-            return self.parse_add_expr();
+    ///A statement that starts with an operator continues from the previous result: `+40;` or `+=40;`
+    ///The implicit result is picked up as the first operand by parse_postfix_expr().
+    fn parse_continuation(&mut self) {
+        use TokenType::*;
+        let (Plus | Mult | Div | Power | Percent | Modulo | Less | Greater | LessEq | GreaterEq | EqEq | NotEq
+            | EqPlus | EqMin | EqMult | EqDiv | EqUnit) = self.tok.peek().kind else {
+            return;
         };
+        let range = Range { end: self.tok.peek().range.start, ..self.tok.peek().range.clone() };
+        self.continuation = Some(Node::boxed(NodeType::ResultRef(ResultRefExpr {
+            token: Token {
+                kind: LastResult,
+                range,
+                #[cfg(debug_assertions)]
+                text: "".to_string(),
+            },
+            ref_type: ResultRefType::Last,
+            implicit: true,
+        })));
+    }
+
+    fn parse_assign_expr(&mut self) -> Box<Node> {
+        let assignable = self.parse_assignable();
         use TokenType::*;
         let op_type = self.tok.peek().kind.clone();
         let (Eq | EqPlus | EqMin | EqMult | EqDiv | EqUnit) = op_type else {
-            return self.parse_add_expr();
+            return assignable;
         };
-        if let Eq = op_type {
-            let eq = self.tok.next();
-            let assign_expr = AssignExpr {
-                assignable,
-                expr: Parser::reduce_list(Node::boxed(NodeType::List(self.parse_list_expr())))
-            };
-
-            if let NodeType::None(none_expr) = &assign_expr.expr.expr {
-                if none_expr.token.kind == TokenType::Eot {
-                    self.errors.push(errors::eos(Range { start: eq.range.end, ..eq.range }));
-                }
-            }
-
-            let txt = self.globals.get_text(&assign_expr.assignable.id.range).to_string();
-            self.code_block.scope.borrow_mut().var_defs.insert(txt);
-            return Node::boxed(NodeType::Assign(assign_expr));
-        }
-        //build this expression: AssignExpr {id, BinExpr{ IdExpr, op, expr }}
+        //whether the assignable is valid, is checked by the resolver.
         let eq_op = self.tok.next();
-        let id_expr = IdExpr {
-            id: assignable.id.clone(),
-        };
-
-        let expr : Box<Node> = match op_type {
-            EqPlus | EqMin | EqMult | EqDiv => {
-                let bin_op = match op_type {
-                    EqPlus => Plus,
-                    EqMin => Min,
-                    EqMult => Mult,
-                    EqDiv => Div,
-                    _ => unreachable!()
-                };
-                Node::boxed(NodeType::Binary(BinExpr {
-                    expr1: Node::boxed(NodeType::Id(id_expr)),
-                    op: Token {
-                        kind: bin_op,
-                        range: eq_op.range,
-                        #[cfg(debug_assertions)]
-                        text: "Eq_xxx".to_string(),
-                    } ,
-                    expr2: self.parse_add_expr(),
-                    implicit_mult: false,
-                }))
+        let expr = match op_type {
+            Eq => {
+                let expr = Parser::reduce_list(Node::boxed(NodeType::List(self.parse_list_expr())));
+                if let NodeType::None(none_expr) = &expr.expr {
+                    if none_expr.token.kind == TokenType::Eot {
+                        self.errors.push(errors::eos(Range { start: eq_op.range.end, ..eq_op.range.clone() }));
+                    }
+                }
+                if let NodeType::Id(id_expr @ IdExpr { selector: None, .. }) = &assignable.expr {
+                    let txt = self.globals.get_text(&id_expr.id.range).to_string();
+                    let is_function_ref = self.is_function_ref(&expr);
+                    let mut scope = self.code_block.scope.borrow_mut();
+                    scope.var_defs.insert(txt.clone());
+                    if is_function_ref {
+                        scope.function_ref_defs.insert(txt);
+                    } else {
+                        scope.function_ref_defs.remove(&txt);
+                    }
+                }
+                expr
             },
+            EqPlus | EqMin | EqMult | EqDiv => self.parse_add_expr(),
             EqUnit => {
                 let id_token = if self.tok.peek().kind == TokenType::Id { //assume id is a variable with a unit we'd like to apply.
                     self.tok.next()
@@ -371,28 +647,200 @@ impl<'g, 'a, 't> Parser<'g, 'a, 't> {
                         text: "".to_string(),
                     }
                 };
-                Node::boxed(NodeType::Postfix(PostfixExpr {
-                    node: Node::boxed(NodeType::Id(id_expr)),
-                    postfix_id: id_token,
-                }))
+                Node::boxed(NodeType::Id(IdExpr { id: id_token, selector: None }))
             },
             _ => unreachable!("expected a Eq operator.")
         };
 
-        let assign_expr = AssignExpr {
+        Node::boxed(NodeType::Assign(AssignExpr {
             assignable,
+            op: eq_op,
             expr,
+        }))
+    }
+
+    ///A lambda, the name of a function or a call that returns a lambda, so that a variable that is assigned this expression can be called: `f(2)`.
+    fn is_function_ref(&self, expr: &Node) -> bool {
+        match &expr.expr {
+            NodeType::Lambda(_) => true,
+            NodeType::Call(call_expr) => self.code_block.scope.borrow().returns_lambda(&call_expr.function_name),
+            NodeType::Id(IdExpr { id, selector: None }) => {
+                let name = self.globals.get_text(&id.range);
+                let scope = self.code_block.scope.borrow();
+                scope.function_ref_defs.contains(name) || (!scope.var_defs.contains(name) && scope.function_exists(name, self.globals))
+            },
+            _ => false,
+        }
+    }
+
+    fn parse_assignable(&mut self) -> Box<Node> {
+        use TokenType::*;
+        //a plain id is always an assignable, even if it's also a function name: `sin=123;`
+        if self.tok.peek().kind == Id {
+            if let Eq | EqPlus | EqMin | EqMult | EqDiv | EqUnit = self.tok.peek_second().kind {
+                return Node::boxed(NodeType::Id(IdExpr { id: self.tok.next(), selector: None }));
+            }
+        }
+        self.parse_ternary_expr()
+    }
+
+    fn parse_ternary_expr(&mut self) -> Box<Node> {
+        if let Some(arg_names) = self.peek_lambda_params() {
+            return self.parse_lambda_expr(arg_names);
+        }
+        let condition = self.parse_comparison_expr();
+        if !self.match_token(&TokenType::Question) {
+            return condition;
+        }
+        let then_expr = self.parse_ternary_expr();
+        if !self.match_token(&TokenType::Colon) {
+            self.errors.push(errors::expected(":", self.tok.peek().range.clone()));
+            let mut node = Node::boxed(NodeType::None(NoneExpr { token: self.tok.peek().clone() }));
+            node.has_errors = true;
+            return node;
+        }
+        let else_expr = self.parse_ternary_expr();
+        Node::boxed(NodeType::Ternary(TernaryExpr { condition, then_expr, else_expr }))
+    }
+
+    ///Returns the parameter names if a lambda starts here: `x => ...` or `(x, y) => ...`
+    fn peek_lambda_params(&mut self) -> Option<Vec<String>> {
+        let mut params = Vec::new();
+        match self.tok.peek().kind {
+            TokenType::Id => {
+                if self.tok.peek_second().kind != TokenType::Arrow {
+                    return None;
+                }
+                params.push(self.tok.peek().range.clone());
+            },
+            TokenType::ParOpen => {
+                let mut lookahead = self.tok.clone();
+                lookahead.next(); // `(`
+                while lookahead.peek().kind == TokenType::Id {
+                    params.push(lookahead.next().range);
+                    if lookahead.peek().kind != TokenType::Comma {
+                        break;
+                    }
+                    lookahead.next();
+                }
+                if lookahead.next().kind != TokenType::ParClose || lookahead.next().kind != TokenType::Arrow {
+                    return None;
+                }
+            },
+            _ => return None
+        }
+        Some(params.iter().map(|range| self.globals.get_text(range).to_string()).collect())
+    }
+
+    ///The body is parsed as a single statement in a new scope that can read the variables of the current scope.
+    fn parse_lambda_expr(&mut self, arg_names: Vec<String>) -> Box<Node> {
+        let start = self.tok.peek().range.clone();
+        while self.tok.next().kind != TokenType::Arrow {} //already checked by peek_lambda_params()
+
+        let new_scope = Scope::copy_for_block(&self.code_block.scope);
+        {
+            let mut scope = new_scope.borrow_mut();
+            scope.inherit_variables = true;
+            scope.var_defs = self.code_block.scope.borrow().var_defs.clone();
+            scope.var_defs.extend(arg_names.iter().cloned());
+            scope.function_ref_defs = self.code_block.scope.borrow().function_ref_defs.clone();
+            for arg_name in &arg_names {
+                scope.function_ref_defs.remove(arg_name); //a parameter hides the variable.
+            }
+        }
+        let mut code_block = CodeBlock::new(new_scope, start.clone());
+        let error_count = self.errors.len();
+        let body = {
+            let mut parser = Parser::new(self.globals, self.tok, self.errors, code_block);
+            let body = parser.parse_ternary_expr();
+            code_block = parser.code_block;
+            body
+        };
+        code_block.append_errors(&self.errors[error_count..].to_vec());
+        let range = &start + &body.get_range();
+        code_block.statements.push(Statement { node: body, mute: false, echo: None });
+
+        let function_name = format!("lambda@{}.{}", start.source_index.as_int(), start.start);
+        let function_def_expr = FunctionDefExpr {
+            id: function_name.clone(),
+            name: self.globals.get_text(&range).to_string(), //for the errors: a lambda is only called by its id.
+            id_range: start,
+            arg_names,
+            variadic: false,
+            range: range.clone(),
         };
-        Node::boxed(NodeType::Assign(assign_expr))
+        self.code_block.scope.borrow_mut().add_local_function(code_block, &function_def_expr, Vec::new());
+        Node::boxed(NodeType::Lambda(LambdaExpr { function_name, range }))
     }
 
-    fn parse_assignable(&mut self) -> Option<AssignableExpr> {
-        if self.tok.peek().kind != TokenType::Id {
-            return None;
+    ///Parses the part following an id that turns it into a selector: `a#`, `a*` or `a..z`.
+    fn parse_selector(&mut self) -> Option<Selector> {
+        match self.tok.peek().kind {
+            TokenType::MuteLine => {
+                Some(Selector { selector_type: SelectorType::NumericSuffix, range: self.tok.next().range })
+            },
+            TokenType::Mult => {
+                //only a selector if there's no second operand: `sum(a*)`
+                if !matches!(self.tok.peek_second().kind, TokenType::ParClose | TokenType::BracClose | TokenType::CurlClose | TokenType::Comma | TokenType::SemiColon | TokenType::Eot) {
+                    return None;
+                }
+                Some(Selector { selector_type: SelectorType::Prefix, range: self.tok.next().range })
+            },
+            TokenType::Dot => {
+                if self.tok.peek_second().kind != TokenType::Dot {
+                    return None;
+                }
+                let start = self.tok.next().range;
+                self.tok.next();
+                if self.tok.peek().kind != TokenType::Id {
+                    self.errors.push(errors::expected_id(self.tok.peek().range.clone()));
+                    return None;
+                }
+                let last = self.tok.next();
+                Some(Selector { range: &start + &last.range, selector_type: SelectorType::Range { last } })
+            },
+            _ => None
+        }
+    }
+
+    fn parse_comparison_expr(&mut self) -> Box<Node> {
+        let mut expr1 = self.parse_range_expr();
+        loop {
+            match self.tok.peek().kind {
+                TokenType::Less | TokenType::Greater | TokenType::LessEq | TokenType::GreaterEq | TokenType::EqEq | TokenType::NotEq => {
+                    let op = self.tok.next().clone();
+                    let expr2 = self.parse_range_expr();
+                    expr1 = Node::boxed(NodeType::Binary(BinExpr { expr1, op, expr2, implicit_mult: false }))
+                }
+                TokenType::Approx => {
+                    let op = self.tok.next().clone();
+                    let expr2 = self.parse_range_expr();
+                    let tolerance = if self.match_token(&TokenType::Within) {
+                        Some(self.parse_range_expr())
+                    } else {
+                        None
+                    };
+                    expr1 = Node::boxed(NodeType::Approx(ApproxExpr { expr1, op, expr2, tolerance }))
+                }
+                _ => break
+            }
+        };
+        expr1
+    }
+
+    fn parse_range_expr(&mut self) -> Box<Node> {
+        let start = self.parse_add_expr();
+        if self.tok.peek().kind != TokenType::Ellipsis {
+            return start;
         }
-        let id =self.tok.next();
-        Some(AssignableExpr { id, fragment: None })
-        //TODO: check for fragment.
+        self.tok.next();
+        let end = self.parse_add_expr();
+        let step = if self.match_token(&TokenType::Step) {
+            Some(self.parse_add_expr())
+        } else {
+            None
+        };
+        Node::boxed(NodeType::Range(RangeExpr { start, end, step }))
     }
 
     fn parse_add_expr(&mut self) -> Box<Node> {
@@ -468,7 +916,7 @@ impl<'g, 'a, 't> Parser<'g, 'a, 't> {
                 text: "implicit mult".to_string(),
             };
             let n2 = if t.kind == TokenType::ParOpen {
-                Parser::reduce_list(Node::boxed(NodeType::List(self.parse_list_expr())))
+                self.parse_ternary_expr() //not a list: in `max(2(3), 4)`, the 4 is the next argument.
             } else {
                 let pfix = self.parse_postfix_expr();
                 // if n1 and n2 are numbers -> error
@@ -510,12 +958,18 @@ impl<'g, 'a, 't> Parser<'g, 'a, 't> {
     }
 
     fn parse_postfix_expr(&mut self) -> Box<Node> {
-        let mut expr = self.parse_unit_expr();
+        let mut expr = match self.continuation.take() {
+            Some(result_ref) => result_ref,
+            None => self.parse_unit_expr()
+        };
         loop {
             match self.tok.peek().kind {
                 TokenType::Dot | TokenType::Dec | TokenType::Inc | TokenType::Exclam => {
                     expr = self.parse_one_postfix(expr);
                 },
+                TokenType::BracOpen => {
+                    expr = self.parse_index_expr(expr);
+                },
                 _ => break
             }
         }
@@ -557,6 +1011,40 @@ impl<'g, 'a, 't> Parser<'g, 'a, 't> {
         }
     }
 
+    fn parse_index_expr(&mut self, node: Box<Node>) -> Box<Node> {
+        self.tok.next(); // [
+        let start = if self.tok.peek().kind == TokenType::Colon {
+            None
+        } else {
+            Some(self.parse_add_expr())
+        };
+        let index = if self.match_token(&TokenType::Colon) {
+            let end = if self.tok.peek().kind == TokenType::BracClose {
+                None
+            } else {
+                Some(self.parse_add_expr())
+            };
+            IndexType::Slice { start, end }
+        } else {
+            IndexType::Single(start.unwrap()) //unwrap: start is only None if followed by a colon.
+        };
+        let has_errors = self.tok.peek().kind != TokenType::BracClose;
+        if has_errors {
+            self.errors.push(errors::expected("]", self.tok.peek().range.clone()));
+        }
+        let mut node = Node::boxed(NodeType::Index(IndexExpr {
+            node,
+            index,
+            brac_close_range: self.tok.peek().range.clone(),
+        }));
+        if has_errors {
+            node.has_errors = true;
+        } else {
+            self.tok.next();
+        }
+        node
+    }
+
     fn create_call_for_operator(&mut self, function_name: &str, arg:  Box<Node>, range: &Range) -> Box<Node> {
         Node::boxed(NodeType::Call( CallExpr {
             function_name: function_name.to_string(),
@@ -640,11 +1128,16 @@ impl<'g, 'a, 't> Parser<'g, 'a, 't> {
             TokenType::Id => {
                 let t = self.tok.next();
                 let id = self.globals.get_text(&t.range);
-                if self.code_block.scope.borrow().function_exists(id, self.globals) {
+                //without parentheses, a function name is a reference to the function.
+                //A variable that is assigned a function is called like one: `f = x => x*2; f(3);`
+                if self.tok.peek().kind == TokenType::ParOpen
+                    && (self.code_block.scope.borrow().function_exists(id, self.globals) || self.code_block.scope.borrow().function_ref_defs.contains(id)) {
                     return self.parse_call_expr(t);
                 }
+                let selector = self.parse_selector();
                 Node::boxed(NodeType::Id(IdExpr {
                     id: t,
+                    selector,
                 }))
             }
             TokenType::ParOpen => {
@@ -663,6 +1156,22 @@ impl<'g, 'a, 't> Parser<'g, 'a, 't> {
                 let t = self.tok.next().clone();
                 self.parse_abs_operator(t)
             },
+            TokenType::LastResult => {
+                Node::boxed(NodeType::ResultRef(ResultRefExpr {
+                    token: self.tok.next(),
+                    ref_type: ResultRefType::Last,
+                    implicit: false,
+                }))
+            },
+            TokenType::LineRef => {
+                let t = self.tok.next();
+                let number = self.globals.get_text(&t.range)[1..].parse().unwrap_or(0); //only digits, but may overflow.
+                Node::boxed(NodeType::ResultRef(ResultRefExpr {
+                    token: t,
+                    ref_type: if self.echo_template { ResultRefType::Echo(Some(number)) } else { ResultRefType::Line(number) },
+                    implicit: false,
+                }))
+            },
             TokenType::QuotedStr => {
                 let t = self.tok.next();
                 Node::boxed(NodeType::Const(ConstExpr {
@@ -670,11 +1179,76 @@ impl<'g, 'a, 't> Parser<'g, 'a, 't> {
                     range: t.range,
                 }))
             }
+            TokenType::DoubleQuotedStr => {
+                let t = self.tok.next();
+                Node::boxed(NodeType::Const(ConstExpr {
+                    const_type: ConstType::Text,
+                    range: t.range,
+                }))
+            }
+            TokenType::CurlOpen => self.parse_record_expr(),
+            TokenType::BracOpen => self.parse_matrix_expr(),
             // if nothing meaningfull found, don't report an error yet as this will be too generic : "Unexpected..."
             _ => Node::boxed(NodeType::None(NoneExpr { token: self.tok.peek().clone()}))
         }
     }
 
+    ///A statement that starts with `{name:` is a record, not a code block.
+    fn is_record_start(&self) -> bool {
+        let mut lookahead = self.tok.clone();
+        lookahead.next(); // `{`
+        lookahead.next().kind == TokenType::Id && lookahead.next().kind == TokenType::Colon
+    }
+
+    ///Rows are separated by `;`
+    fn parse_matrix_expr(&mut self) -> Box<Node> {
+        let brac_open = self.tok.next().range;
+        let mut rows = Vec::new();
+        loop {
+            let list_expr = self.parse_list_expr();
+            if let [node] = list_expr.nodes.as_slice() {
+                if let NodeType::None(_) = node.expr {
+                    break; //empty row.
+                }
+            }
+            rows.push(list_expr.nodes);
+            if !self.match_token(&TokenType::SemiColon) {
+                break;
+            }
+        }
+        let brac_close = self.tok.peek().range.clone();
+        let mut node = Node::boxed(NodeType::Matrix(MatrixExpr { brac_open, rows, brac_close }));
+        if !self.match_token(&TokenType::BracClose) {
+            self.errors.push(errors::expected("]", self.tok.peek().range.clone()));
+            node.has_errors = true;
+        }
+        node
+    }
+
+    fn parse_record_expr(&mut self) -> Box<Node> {
+        let curl_open = self.tok.next().range;
+        let mut fields = Vec::new();
+        while self.tok.peek().kind == TokenType::Id {
+            let name = self.tok.next().range;
+            if !self.match_token(&TokenType::Colon) {
+                self.errors.push(errors::expected(":", self.tok.peek().range.clone()));
+                break;
+            }
+            let node = self.parse_ternary_expr();
+            fields.push(RecordField { name, node });
+            if !self.match_token(&TokenType::Comma) {
+                break;
+            }
+        }
+        let curl_close = self.tok.peek().range.clone();
+        let mut node = Node::boxed(NodeType::Record(RecordExpr { curl_open, fields, curl_close }));
+        if !self.match_token(&TokenType::CurlClose) {
+            self.errors.push(errors::expected("}", self.tok.peek().range.clone()));
+            node.has_errors = true;
+        }
+        node
+    }
+
     fn parse_abs_operator(&mut self, token: Token) -> Box<Node> {
         let expr = self.parse_add_expr();
         if self.tok.peek().kind != TokenType::Pipe {
@@ -709,7 +1283,7 @@ impl<'g, 'a, 't> Parser<'g, 'a, 't> {
     fn parse_list_expr(&mut self) -> ListExpr {
         let mut list_expr = ListExpr { nodes: Vec::new()};
         loop {
-            let expr = self.parse_add_expr();
+            let expr = self.parse_ternary_expr();
             list_expr.nodes.push(expr);
             if let NodeType::None(_) = list_expr.nodes.last().unwrap().expr { //unwrap: push() guarantees there's a last()
                 break;
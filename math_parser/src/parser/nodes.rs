@@ -44,17 +44,27 @@ pub enum NodeType {
     Comment(CommentExpr),
     Assign(AssignExpr),
     Binary(BinExpr),
+    Approx(ApproxExpr),
     Unary(UnaryExpr),
     Const(ConstExpr),
     Id(IdExpr),
     Postfix(PostfixExpr),
     List(ListExpr),
+    Record(RecordExpr),
+    Matrix(MatrixExpr),
     FunctionDef(FunctionDefExpr),
+    ConstDef(ConstDefExpr),
     Call(CallExpr),
     Block(CodeBlock),
     Define(DefineExpr),
     Pragma(PragmaExpr),
-    Assignable(AssignableExpr)
+    Include(IncludeExpr),
+    If(IfExpr),
+    Ternary(TernaryExpr),
+    Range(RangeExpr),
+    Index(IndexExpr),
+    ResultRef(ResultRefExpr),
+    Lambda(LambdaExpr),
 }
 
 impl NodeType {
@@ -78,17 +88,27 @@ impl HasRange for NodeType {
             N::Comment(expr) => expr.get_range(),
             N::Assign(expr) => expr.get_range(),
             N::Binary(expr) => expr.get_range(),
+            N::Approx(expr) => expr.get_range(),
             N::Unary(expr) => expr.get_range(),
             N::Const(expr) => expr.get_range(),
             N::Id(expr) => expr.get_range(),
             N::Postfix(expr) => expr.get_range(),
             N::List(expr) => expr.get_range(),
+            N::Record(expr) => expr.get_range(),
+            N::Matrix(expr) => expr.get_range(),
             N::FunctionDef(expr) => expr.get_range(),
+            N::ConstDef(expr) => expr.get_range(),
             N::Call(expr) => expr.get_range(),
             N::Block(expr) => expr.get_range(),
             N::Define(expr) => expr.get_range(),
             N::Pragma(expr) => expr.get_range(),
-            N::Assignable(expr) => expr.get_range(),
+            N::Include(expr) => expr.get_range(),
+            N::If(expr) => expr.get_range(),
+            N::Ternary(expr) => expr.get_range(),
+            N::Range(expr) => expr.get_range(),
+            N::Index(expr) => expr.get_range(),
+            N::ResultRef(expr) => expr.get_range(),
+            N::Lambda(expr) => expr.get_range(),
         }
     }
 }
@@ -113,9 +133,18 @@ impl HasRange for UnitExpr {
     }
 }
 
-#[derive(Clone)]
+///An echo comment line `!// ...`, split in parts: plain text, embedded expressions and result placeholders.
 pub struct CommentExpr {
     pub token: Token,
+    pub parts: Vec<EchoPart>,
+}
+
+pub enum EchoPart {
+    Text(Range),
+    ///`` `a+b` `` outputs the result, `` !`a+b` `` outputs the code.
+    Expr { node: Box<Node>, echo_code: bool },
+    ///`$` or `$0`, `$1`,...
+    Result(ResultRefExpr),
 }
 
 impl HasRange for CommentExpr {
@@ -124,23 +153,23 @@ impl HasRange for CommentExpr {
     }
 }
 
-pub struct AssignableExpr {
-    pub id: Token,
-    pub fragment: Option<Token>,
+///`const name = value;` The `assign` node is always an `AssignExpr` to a variable.
+pub struct ConstDefExpr {
+    pub const_token: Token,
+    pub assign: Box<Node>,
 }
 
-impl HasRange for AssignableExpr {
+impl HasRange for ConstDefExpr {
     fn get_range(&self) -> Range {
-        let mut range = self.id.range.clone();
-        if let Some(fragment)  = &self.fragment {
-            range = &range + &fragment.range;
-        }
-        range
+        &self.const_token.range + &self.assign.get_range()
     }
 }
 
+///`assignable` can be any expression: the resolver checks if it's something that can be assigned to.
+///For `.=` the `expr` is an `IdExpr` holding the unit (or a `ClearUnit` token).
 pub struct AssignExpr {
-    pub assignable: AssignableExpr,
+    pub assignable: Box<Node>,
+    pub op: Token,
     pub expr: Box<Node>,
 }
 
@@ -163,6 +192,71 @@ impl HasRange for BinExpr {
     }
 }
 
+///`a ~= b` or `a ~= b within 0.01`
+pub struct ApproxExpr {
+    pub expr1: Box<Node>,
+    pub op: Token,
+    pub expr2: Box<Node>,
+    pub tolerance: Option<Box<Node>>,
+}
+
+impl HasRange for ApproxExpr {
+    fn get_range(&self) -> Range {
+        let range = &self.expr1.get_range() + &self.expr2.get_range();
+        match &self.tolerance {
+            Some(tolerance) => &range + &tolerance.get_range(),
+            None => range,
+        }
+    }
+}
+
+pub struct IfExpr {
+    pub if_token: Token,
+    pub condition: Box<Node>,
+    pub then_block: CodeBlock,
+    pub else_node: Option<Box<Node>>, //either a Block or another If.
+}
+
+impl HasRange for IfExpr {
+    fn get_range(&self) -> Range {
+        let range = &self.if_token.range + &self.then_block.get_range();
+        if let Some(else_node) = &self.else_node {
+            &range + &else_node.get_range()
+        } else {
+            range
+        }
+    }
+}
+
+pub struct TernaryExpr {
+    pub condition: Box<Node>,
+    pub then_expr: Box<Node>,
+    pub else_expr: Box<Node>,
+}
+
+impl HasRange for TernaryExpr {
+    fn get_range(&self) -> Range {
+        &self.condition.get_range() + &self.else_expr.get_range()
+    }
+}
+
+pub struct RangeExpr {
+    pub start: Box<Node>,
+    pub end: Box<Node>,
+    pub step: Option<Box<Node>>,
+}
+
+impl HasRange for RangeExpr {
+    fn get_range(&self) -> Range {
+        let range = &self.start.get_range() + &self.end.get_range();
+        if let Some(step) = &self.step {
+            &range + &step.get_range()
+        } else {
+            range
+        }
+    }
+}
+
 pub struct UnaryExpr {
     pub op: Token,
     pub expr: Box<Node>,
@@ -174,7 +268,7 @@ impl HasRange for UnaryExpr {
     }
 }
 
-pub enum ConstType { Numeric {number: Number}, FormattedString }
+pub enum ConstType { Numeric {number: Number}, FormattedString, Text }
 
 pub struct ConstExpr {
     pub const_type: ConstType,
@@ -189,14 +283,29 @@ impl HasRange for ConstExpr {
 
 pub struct IdExpr {
     pub id: Token,
+    pub selector: Option<Selector>, //selects all the variables that match the id, as a list.
 }
 
 impl HasRange for IdExpr {
     fn get_range(&self) -> Range {
-        self.id.range.clone()
+        match &self.selector {
+            Some(selector) => &self.id.range + &selector.range,
+            None => self.id.range.clone()
+        }
     }
 }
 
+pub enum SelectorType {
+    NumericSuffix, // a#
+    Prefix, // a*
+    Range { last: Token }, // a..z
+}
+
+pub struct Selector {
+    pub selector_type: SelectorType,
+    pub range: Range, //excluding the id.
+}
+
 pub struct PostfixExpr {
     pub node: Box<Node>,
     pub postfix_id: Token,
@@ -212,9 +321,47 @@ impl HasRange for PostfixExpr {
     }
 }
 
+pub enum IndexType {
+    Single(Box<Node>),
+    Slice { start: Option<Box<Node>>, end: Option<Box<Node>> },
+}
+
+pub struct IndexExpr {
+    pub node: Box<Node>,
+    pub index: IndexType,
+    pub brac_close_range: Range,
+}
+
+impl HasRange for IndexExpr {
+    fn get_range(&self) -> Range {
+        &self.node.get_range() + &self.brac_close_range
+    }
+}
+
+pub enum ResultRefType {
+    Last,
+    Line(usize),
+    ///Within an echo comment: `$0` is the result of the first embedded expression, `$` the latest one.
+    Echo(Option<usize>),
+}
+
+///`_`, `$3` or the implicit previous result of a continuation statement like `+40;`
+pub struct ResultRefExpr {
+    pub token: Token,
+    pub ref_type: ResultRefType,
+    pub implicit: bool,
+}
+
+impl HasRange for ResultRefExpr {
+    fn get_range(&self) -> Range {
+        self.token.range.clone()
+    }
+}
+
 pub struct Statement {
     pub node: Box<Node>,
-    pub mute: bool
+    pub mute: bool,
+    pub echo: Option<Range>, //the source text to echo with the result.
 }
 
 impl HasRange for Statement {
@@ -228,7 +375,8 @@ impl Statement {
         errors.push( error);
         Statement {
             node: Box::new( Node { expr: N::None(NoneExpr { token }), unit: Unit::none(), has_errors: true }),
-            mute: false
+            mute: false,
+            echo: None,
         }
     }
 
@@ -238,6 +386,37 @@ impl Statement {
     }
 }
 
+pub struct RecordField {
+    pub name: Range,
+    pub node: Box<Node>,
+}
+
+///`{x: 3m, y: 4m}`
+pub struct RecordExpr {
+    pub curl_open: Range,
+    pub fields: Vec<RecordField>,
+    pub curl_close: Range,
+}
+
+impl HasRange for RecordExpr {
+    fn get_range(&self) -> Range {
+        &self.curl_open + &self.curl_close
+    }
+}
+
+///`[1, 2; 3, 4]`
+pub struct MatrixExpr {
+    pub brac_open: Range,
+    pub rows: Vec<Vec<Box<Node>>>,
+    pub brac_close: Range,
+}
+
+impl HasRange for MatrixExpr {
+    fn get_range(&self) -> Range {
+        &self.brac_open + &self.brac_close
+    }
+}
+
 pub struct ListExpr {
     pub nodes: Vec<Box<Node>>,
 }
@@ -254,8 +433,10 @@ impl HasRange for ListExpr {
 #[derive(Clone)]
 pub struct FunctionDefExpr {
     pub id: String, //Not a Token because id may be a decorated name in case of polymorphism.
-    pub id_range: Range, //the undecorated functionname
+    pub name: String, //the undecorated functionname
+    pub id_range: Range,
     pub arg_names: Vec<String>,
+    pub variadic: bool, //the last arg collects the remaining arguments in a list.
     pub range: Range,
     //don't add CodeBlock as it can't moved out of the (immutable) AST and it can't be referenced from the AST without lifetime issues
 }
@@ -266,6 +447,18 @@ impl HasRange for FunctionDefExpr {
     }
 }
 
+///The body of the lambda is added to the scope as a local function with a decorated name.
+pub struct LambdaExpr {
+    pub function_name: String,
+    pub range: Range,
+}
+
+impl HasRange for LambdaExpr {
+    fn get_range(&self) -> Range {
+        self.range.clone()
+    }
+}
+
 pub struct CallExpr {
     pub function_name: String, //this may not be a stream range, but a translated function name: e.g. x++ -> _inc(x)
     pub function_name_range: Range,
@@ -296,6 +489,16 @@ impl CodeBlock {
         }
     }
 
+    ///A code block that shares the scope of another block, used to parse expressions that are not part of the statements.
+    pub fn with_scope(scope: Rc<RefCell<Scope>>, block_start: Range) -> Self {
+        CodeBlock {
+            block_start,
+            scope,
+            statements: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+
     pub fn get_parser_errors(&self) -> &Vec<Error> {
         &self.errors
     }
@@ -309,6 +512,7 @@ impl HasRange for CodeBlock {
     fn get_range(&self) -> Range {
         &self.block_start +  &self.statements.iter()
             .map(|stmt| stmt.get_range())
+            .filter(|range| range.source_index == self.block_start.source_index) //skip included statements.
             .reduce(|sum, range| &sum + &range).unwrap_or(self.block_start.clone())
     }
 }
@@ -318,6 +522,8 @@ pub enum DefineType {
     Ymd,
     Mdy,
     Precision { number: Number },
+    Recursion { number: Number },
+    Physics,
     DateUnits,
     ShortDateUnits,
     Trig,
@@ -372,6 +578,18 @@ impl HasRange for PragmaExpr {
     }
 }
 
+///The statements of the included source are added to the code block. This node only marks the directive itself.
+pub struct IncludeExpr {
+    pub include_token: Token,
+    pub name_range: Range,
+}
+
+impl HasRange for IncludeExpr {
+    fn get_range(&self) -> Range {
+        &self.include_token.range + &self.name_range
+    }
+}
+
 
 #[allow(unused)]
 pub fn print_nodes(node: &Box<Node>, indent: usize, globals: &Globals) {
@@ -381,7 +599,7 @@ pub fn print_nodes(node: &Box<Node>, indent: usize, globals: &Globals) {
         N::Const(expr) => {
             let value_str = match &expr.const_type {
                 ConstType::Numeric { number } => number.to_double().to_string(),
-                ConstType::FormattedString => globals.get_text(&expr.range).to_string()
+                ConstType::FormattedString | ConstType::Text => globals.get_text(&expr.range).to_string()
             };
             println!("{0}: {1}{2}", "ConstExpr", value_str, node.unit.id);
         },
@@ -390,6 +608,12 @@ pub fn print_nodes(node: &Box<Node>, indent: usize, globals: &Globals) {
             print_nodes(&expr.expr1, indent, globals);
             print_nodes(&expr.expr2, indent, globals);
         },
+        N::Approx(expr) => {
+            println!("{0}", "ApproxExpr");
+            print_nodes(&expr.expr1, indent, globals);
+            print_nodes(&expr.expr2, indent, globals);
+            expr.tolerance.iter().for_each(|tolerance| print_nodes(tolerance, indent, globals));
+        },
         N::None(expr) => {
             println!("{0}", "NoneExpr");
         },
@@ -399,6 +623,18 @@ pub fn print_nodes(node: &Box<Node>, indent: usize, globals: &Globals) {
                 print_nodes(&child, indent, globals);
             }
         },
+        N::Matrix(expr) => {
+            println!("{0}", "MatrixExpr");
+            for node in expr.rows.iter().flatten() {
+                print_nodes(node, indent, globals);
+            }
+        },
+        N::Record(expr) => {
+            println!("{0}", "RecordExpr");
+            for field in &expr.fields {
+                print_nodes(&field.node, indent, globals);
+            }
+        },
         N::Assign(expr) => {
             println!("{0}", "AssignExpr");
             print_nodes(&expr.expr, indent, globals);
@@ -418,12 +654,57 @@ pub fn print_nodes(node: &Box<Node>, indent: usize, globals: &Globals) {
         N::FunctionDef(expr) => {
             println!("{0}", "FunctionDefExpr");
         },
+        N::ConstDef(expr) => {
+            println!("{0}", "ConstDefExpr");
+            print_nodes(&expr.assign, indent, globals);
+        },
+        N::Lambda(expr) => {
+            println!("{0}{1}", "LambdaExpr: ", expr.function_name);
+        },
         N::Unit(expr) => {
             println!("{0}", "UnitExpr");
         },
         N::Define(expr) => {
             println!("{0}", "DefineExpr");
         },
+        N::If(expr) => {
+            println!("{0}", "IfExpr");
+            print_nodes(&expr.condition, indent, globals);
+            for stmt in &expr.then_block.statements {
+                print_nodes(&stmt.node, indent, globals);
+            }
+            if let Some(else_node) = &expr.else_node {
+                print_nodes(else_node, indent, globals);
+            }
+        },
+        N::Ternary(expr) => {
+            println!("{0}", "TernaryExpr");
+            print_nodes(&expr.condition, indent, globals);
+            print_nodes(&expr.then_expr, indent, globals);
+            print_nodes(&expr.else_expr, indent, globals);
+        },
+        N::Range(expr) => {
+            println!("{0}", "RangeExpr");
+            print_nodes(&expr.start, indent, globals);
+            print_nodes(&expr.end, indent, globals);
+            if let Some(step) = &expr.step {
+                print_nodes(step, indent, globals);
+            }
+        },
+        N::Index(expr) => {
+            println!("{0}", "IndexExpr");
+            print_nodes(&expr.node, indent, globals);
+            match &expr.index {
+                IndexType::Single(index) => print_nodes(index, indent, globals),
+                IndexType::Slice { start, end } => {
+                    start.iter().for_each(|start| print_nodes(start, indent, globals));
+                    end.iter().for_each(|end| print_nodes(end, indent, globals));
+                }
+            }
+        },
+        N::ResultRef(expr) => {
+            println!("{0}{1}", "ResultRefExpr: ", globals.get_text(&expr.token.range));
+        },
         N::Block(expr) => {
             println!("{0}", "CodeBlock");
             for stmt in &expr.statements {
@@ -1,28 +1,36 @@
 pub mod value;
 pub mod operator;
 pub mod scope;
-mod serialize;
+pub mod serialize;
 pub mod unit;
 pub mod recursive_iterator;
+mod solver;
+mod symbolic;
 
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::rc::Rc;
-use crate::date::{parse_date_string, DateFormat, Duration};
+use crate::complex::{Complex, ComplexFormat};
+use crate::date::{month_from_int, parse_date_string, DateFormat, Day, Duration, Timepoint};
 use crate::errors;
 use crate::errors::{count_real_errors, has_real_errors, Error};
-use crate::functions::FunctionType;
-use crate::parser::nodes::{AssignExpr, BinExpr, CallExpr, CodeBlock, CommentExpr, ConstExpr, ConstType, DefineExpr, FunctionDefExpr, HasRange, IdExpr, ListExpr, Node, NodeType, PostfixExpr, Statement, UnaryExpr, UnitExpr};
+use crate::functions::{call_function_ref, to_text, FunctionType, ASSERT_FUNCTIONS};
+use crate::parser::nodes::{AssignExpr, BinExpr, ApproxExpr, CallExpr, CodeBlock, CommentExpr, ConstDefExpr, ConstExpr, EchoPart, ConstType, DefineExpr, FunctionDefExpr, HasRange, IdExpr, IfExpr, IndexExpr, MatrixExpr, RecordExpr, IndexType, LambdaExpr, ListExpr, Node, NodeType, PostfixExpr, RangeExpr, ResultRefExpr, ResultRefType, SelectorType, Statement, TernaryExpr, UnaryExpr, UnitExpr};
 use crate::globals::Globals;
+use crate::matrix::Matrix;
 use crate::number::{parse_formatted_number, Number};
 use crate::number_format::NumberFormat;
 use crate::resolver::operator::{operator_id_from, OperatorType};
-use crate::resolver::scope::{DecimalChar, Scope};
+use crate::resolver::scope::{DecimalChar, Scope, MAX_RECURSION};
 use crate::resolver::unit::{Unit, UnitProperty, UnitTag, UnitsView};
 use crate::resolver::value::{Value, Variant};
 use crate::resolver::value::Variant::Numeric;
 use crate::tokenizer::cursor::Range;
+use crate::tokenizer::Token;
 use crate::tokenizer::token_type::TokenType;
 
+const MAX_RANGE_SIZE: usize = 100000;
+
 pub struct Resolver<'g, 'a> {
     pub globals: &'g Globals,
     pub scope: Rc<RefCell<Scope>>,
@@ -31,6 +39,9 @@ pub struct Resolver<'g, 'a> {
     //date_format: DateFormat,
     pub muted: bool,
     pub current_statement_muted: bool,
+    pub last_result: Option<Value>, // `_`
+    pub echo_results: Vec<Value>, // `$0`, `$1`,... within an echo comment
+    pub line_results: Vec<Value>, // `$1`, `$2`,... the results of all statements, muted or not.
 }
 
 pub fn add_error_value<'s>(errors: &mut Vec<Error>, error: Error) -> Value {
@@ -40,6 +51,16 @@ pub fn add_error_value<'s>(errors: &mut Vec<Error>, error: Error) -> Value {
     value
 }
 
+///A passing assertion is muted, unless it's echoed.
+fn is_passed_assertion(stmt: &Statement, value: &Value) -> bool {
+    let NodeType::Call(call_expr) = &stmt.node.expr else {
+        return false;
+    };
+    stmt.echo.is_none()
+        && ASSERT_FUNCTIONS.contains(&call_expr.function_name.as_str())
+        && matches!(value.variant, Variant::Bool { value: true })
+}
+
 impl<'g, 'a> Resolver<'g, 'a> {
 
     pub fn resolve(&mut self, statements: &Vec<Statement>) -> Option<Value> {
@@ -47,7 +68,7 @@ impl<'g, 'a> Resolver<'g, 'a> {
 
         for stmt in statements {
             let value = self.resolve_one_statement(stmt);
-            if stmt.mute || self.muted {
+            if stmt.mute || self.muted || is_passed_assertion(stmt, &value) {
                 last_result = Some(value);
             } else {
                 self.results.push(value);
@@ -70,6 +91,11 @@ impl<'g, 'a> Resolver<'g, 'a> {
         self.current_statement_muted = stmt.mute;
         let mut value = self.resolve_node(&stmt.node);
         value.stmt_range = stmt.get_range();
+        value.echo = stmt.echo.clone();
+        if !matches!(value.variant, Variant::FunctionDef | Variant::Comment {..} | Variant::Define | Variant::None) {
+            self.last_result = Some(value.clone());
+        }
+        self.line_results.push(value.clone());
         value
     }
 
@@ -91,6 +117,7 @@ impl<'g, 'a> Resolver<'g, 'a> {
             NodeType::Block(expr) => { self.resolve_codeblock_expr(expr) },
             NodeType::Const(expr) => { self.resolve_const_expr(expr, &node.unit) },
             NodeType::Binary(expr) => { self.resolve_bin_expr(expr, &node.unit) },
+            NodeType::Approx(expr) => { self.resolve_approx_expr(expr, &node.unit) },
             NodeType::Id(expr) => { self.resolve_id_expr(expr, &node.unit) },
             NodeType::Assign(expr) => { self.resolve_assign_expr(expr) },
             NodeType::Unary(expr) => { self.resolve_unary_expr(expr) },
@@ -98,23 +125,29 @@ impl<'g, 'a> Resolver<'g, 'a> {
             NodeType::Unit(expr) => { self.resolve_unit_expr(expr, &node.unit) },
             NodeType::Call(expr) => { self.resolve_call_expr(expr, &node.unit) },
             NodeType::List(expr) => { self.resolve_list_expr(expr) },
+            NodeType::Record(expr) => { self.resolve_record_expr(expr) },
+            NodeType::Matrix(expr) => { self.resolve_matrix_expr(expr) },
             NodeType::Comment(expr) => { self.resolve_comment_expr(expr) },
             NodeType::FunctionDef(expr) => { self.resolve_func_def_expr(expr) },
+            NodeType::ConstDef(expr) => { self.resolve_const_def_expr(expr) },
             NodeType::Define(expr) => { self.resolve_define_expr(expr) },
             NodeType::Pragma(expr) => { Value::none(expr.get_range()) },
+            NodeType::Include(expr) => { Value::none(expr.get_range()) },
             NodeType::None(expr) => { Value::none(expr.get_range()) },
-            NodeType::Assignable(_) => { self.resolve_assignable_expr() }
+            NodeType::If(expr) => { self.resolve_if_expr(expr) },
+            NodeType::Ternary(expr) => { self.resolve_ternary_expr(expr) },
+            NodeType::Range(expr) => { self.resolve_range_expr(expr) },
+            NodeType::Index(expr) => { self.resolve_index_expr(expr, &node.unit) },
+            NodeType::ResultRef(expr) => { self.resolve_result_ref_expr(expr, &node.unit) },
+            NodeType::Lambda(expr) => { self.resolve_lambda_expr(expr) },
         }
     }
 
-    fn resolve_assignable_expr(&mut self) -> Value {
-        unreachable!() //TODO: how is an assignable resolved? Removed this function if not needed.
-    }
-
     fn resolve_codeblock_expr(&mut self, code_block: &CodeBlock) -> Value {
-        let mut resolver = Resolver {globals: self.globals, scope: code_block.scope.clone(), results: Vec::new(), errors: self.errors, muted: self.muted || self.current_statement_muted, current_statement_muted: false};
+        let mut resolver = Resolver {globals: self.globals, scope: code_block.scope.clone(), results: Vec::new(), errors: self.errors, muted: self.muted || self.current_statement_muted, current_statement_muted: false, last_result: self.last_result.clone(), echo_results: Vec::new(), line_results: std::mem::take(&mut self.line_results)};
         let result = resolver.resolve(&code_block.statements);
         self.results.extend(resolver.results);
+        self.line_results = resolver.line_results;
         let Some(mut result) = result else {
             return self.add_error_value(errors::func_no_body("anonymous block", code_block.get_range().clone()));
         };
@@ -122,6 +155,43 @@ impl<'g, 'a> Resolver<'g, 'a> {
         result
     }
 
+    //Only the branch that is taken is resolved.
+    fn resolve_if_expr(&mut self, if_expr: &IfExpr) -> Value {
+        let Some(condition) = self.resolve_condition(&if_expr.condition) else {
+            return Value::error(if_expr.get_range());
+        };
+        if condition {
+            self.resolve_codeblock_expr(&if_expr.then_block)
+        } else if let Some(else_node) = &if_expr.else_node {
+            self.resolve_node(else_node)
+        } else {
+            Value::none(if_expr.get_range())
+        }
+    }
+
+    fn resolve_ternary_expr(&mut self, ternary_expr: &TernaryExpr) -> Value {
+        let Some(condition) = self.resolve_condition(&ternary_expr.condition) else {
+            return Value::error(ternary_expr.get_range());
+        };
+        if condition {
+            self.resolve_node(&ternary_expr.then_expr)
+        } else {
+            self.resolve_node(&ternary_expr.else_expr)
+        }
+    }
+
+    fn resolve_condition(&mut self, condition: &Box<Node>) -> Option<bool> {
+        let value = self.resolve_node(condition);
+        if value.has_errors {
+            return None;
+        }
+        let Variant::Bool { value } = value.variant else {
+            self.errors.push(errors::cond_not_bool(value.variant.name(), condition.get_range()));
+            return None;
+        };
+        Some(value)
+    }
+
     fn resolve_define_expr(&mut self, define_expr: &DefineExpr) -> Value {
         if define_expr.def_undef.kind == TokenType::Define {
             self.resolve_defines(&define_expr);
@@ -133,6 +203,7 @@ impl<'g, 'a> Resolver<'g, 'a> {
             stmt_range: define_expr.get_range(),
             variant: Variant::Define,
             has_errors: false,
+            echo: None,
         }
     }
 
@@ -150,14 +221,31 @@ impl<'g, 'a> Resolver<'g, 'a> {
                     }
                     self.scope.borrow_mut().precision = 10.0_f64.powf(number.to_double());
                 },
+                T::Recursion {ref number} => {
+                    if ! number.is_int() || number.to_double() < 1.0 {
+                        self.errors.push(errors::expected("positive integer value", define.range.clone()));
+                        continue;
+                    }
+                    let mut max_recursion = number.to_double() as usize;
+                    if max_recursion > MAX_RECURSION {
+                        self.errors.push(errors::w_recursion_limit(&MAX_RECURSION.to_string(), define.range.clone()));
+                        max_recursion = MAX_RECURSION;
+                    }
+                    self.scope.borrow_mut().max_recursion = max_recursion;
+                },
                 T::DateUnits => self.scope.borrow_mut().units_view.add_tagged(&UnitTag::LongDateTime, self.globals),
                 T::ShortDateUnits => self.scope.borrow_mut().units_view.add_tagged(&UnitTag::ShortDateTime, self.globals),
                 T::Electric => {
+                    self.scope.borrow_mut().electric = true;
                     self.scope.borrow_mut().units_view.add_class(&UnitProperty::VOLTAGE, &self.globals.unit_defs);
                     self.scope.borrow_mut().units_view.add_class(&UnitProperty::CURRENT, &self.globals.unit_defs);
                     self.scope.borrow_mut().units_view.add_class(&UnitProperty::RESISTANCE, &self.globals.unit_defs);
                 },
                 T::Strict => self.scope.borrow_mut().strict = true,
+                T::Physics => {
+                    self.scope.borrow_mut().physics = true;
+                    self.scope.borrow_mut().units_view.add_physics_classes(&self.globals.unit_defs);
+                },
                 T::DecimalDot => {
                     self.scope.borrow_mut().decimal_char = DecimalChar::Dot;
                 },
@@ -182,11 +270,16 @@ impl<'g, 'a> Resolver<'g, 'a> {
                 DateUnits => self.scope.borrow_mut().units_view.remove_tagged(UnitTag::LongDateTime, &self.globals.unit_defs),
                 ShortDateUnits => self.scope.borrow_mut().units_view.remove_tagged(UnitTag::ShortDateTime, &self.globals.unit_defs),
                 Electric => {
+                    self.scope.borrow_mut().electric = false;
                     self.scope.borrow_mut().units_view.remove_class(&UnitProperty::VOLTAGE, self.globals);
                     self.scope.borrow_mut().units_view.remove_class(&UnitProperty::CURRENT, self.globals);
                     self.scope.borrow_mut().units_view.remove_class(&UnitProperty::RESISTANCE, self.globals);
                 },
                 Strict => self.scope.borrow_mut().strict = true,
+                Physics => {
+                    self.scope.borrow_mut().physics = false;
+                    self.scope.borrow_mut().units_view.remove_physics_classes(self.globals);
+                },
                 Trig => self.scope.borrow_mut().function_view.remove_type(FunctionType::Trig, self.globals),
                 Arithm => self.scope.borrow_mut().function_view.remove_type(FunctionType::Arithm, self.globals),
                 Date => self.scope.borrow_mut().function_view.remove_type(FunctionType::Date, self.globals),
@@ -217,6 +310,208 @@ impl<'g, 'a> Resolver<'g, 'a> {
             stmt_range: list_expr.get_range().clone(),
             variant: Variant::List {values: value_list},
             has_errors: false,
+            echo: None,
+        }
+    }
+
+    fn resolve_record_expr(&mut self, record_expr: &RecordExpr) -> Value {
+        let mut fields: Vec<(String, Value)> = Vec::new();
+        for field in &record_expr.fields {
+            let name = self.globals.get_text(&field.name).to_string();
+            if fields.iter().any(|(existing, _)| *existing == name) {
+                return self.add_error_value(errors::field_redef(&name, field.name.clone()));
+            }
+            let value = self.resolve_node(&field.node);
+            if value.has_errors {
+                return Value::error(record_expr.get_range());
+            }
+            fields.push((name, value));
+        }
+        Value::from_record(fields, record_expr.get_range())
+    }
+
+    ///The elements are stored in SI units.
+    fn resolve_matrix_expr(&mut self, matrix_expr: &MatrixExpr) -> Value {
+        let Some(cols) = matrix_expr.rows.first().map(|row| row.len()) else {
+            return self.add_error_value(errors::inv_matrix("A matrix can't be empty.", matrix_expr.get_range()));
+        };
+        let mut values = Vec::new();
+        let mut si_id = None; //of the first element with a unit. The units of the same property have the same SI unit.
+        let mut unitless_range = None;
+        for row in &matrix_expr.rows {
+            if row.len() != cols {
+                let row_range = row.iter().map(|node| node.get_range()).reduce(|r1, r2| &r1 + &r2).unwrap_or(matrix_expr.get_range());
+                return self.add_error_value(errors::inv_matrix("All rows must have the same number of elements.", row_range));
+            }
+            for node in row {
+                let value = self.resolve_node(node);
+                if value.has_errors {
+                    return Value::error(matrix_expr.get_range());
+                }
+                let Some(number) = value.as_number() else {
+                    return self.add_error_value(errors::inv_matrix("The elements must be numbers.", node.get_range()));
+                };
+                if number.unit.is_empty() {
+                    unitless_range.get_or_insert(node.get_range());
+                } else {
+                    let Some(unit_def) = self.globals.unit_defs.get(&number.unit.id) else {
+                        return self.add_error_value(errors::unit_not_def(&number.unit.id, node.get_range()));
+                    };
+                    if *si_id.get_or_insert(unit_def.si_id) != unit_def.si_id {
+                        return self.add_error_value(errors::unit_prop_diff(node.get_range()));
+                    }
+                }
+                values.push(number.to_si(self.globals).to_double());
+            }
+        }
+        let unit = si_id.map_or(Unit::none(), |si_id| Unit::from_id(si_id, None));
+        if let (false, Some(unitless_range)) = (unit.is_empty(), unitless_range) {
+            self.errors.push(errors::w_assuming_unit(unitless_range));
+        }
+        Value::from_matrix(Matrix::new(matrix_expr.rows.len(), cols, values), unit, matrix_expr.get_range())
+    }
+
+    fn resolve_range_expr(&mut self, range_expr: &RangeExpr) -> Value {
+        let start = self.resolve_node(&range_expr.start);
+        let end = self.resolve_node(&range_expr.end);
+        let step = range_expr.step.as_ref().map(|step| self.resolve_node(step));
+        if start.has_errors || end.has_errors || step.as_ref().is_some_and(|step| step.has_errors) {
+            return Value::error(range_expr.get_range());
+        }
+        match (start.variant, end.variant) {
+            (Variant::Numeric { number: start }, Variant::Numeric { number: end }) => self.resolve_number_range(start, end, step, range_expr),
+            (Variant::Date { date: start }, Variant::Date { date: end }) => self.resolve_date_range(start, end, step, range_expr),
+            (start, end) => {
+                let info = format!("the start and end should both be numbers or dates, but found `{}` and `{}`.", start.name(), end.name());
+                self.add_error_value(errors::inv_range(&info, range_expr.get_range()))
+            }
+        }
+    }
+
+    fn resolve_number_range(&mut self, mut start: Number, mut end: Number, step: Option<Value>, range_expr: &RangeExpr) -> Value {
+        //all values are expressed in the first unit found.
+        let unit = if start.unit.is_empty() { end.unit.clone() } else { start.unit.clone() };
+        let step = match step {
+            None => Number::from(if start.to_double() <= end.to_double() { 1.0 } else { -1.0 }),
+            Some(Value { variant: Variant::Numeric { number }, .. }) => number,
+            Some(value) => {
+                let info = format!("the step should be a number, but found `{}`.", value.variant.name());
+                return self.add_error_value(errors::inv_range(&info, range_expr.get_range()));
+            }
+        };
+        let mut step = step;
+        if !unit.is_empty() {
+            let units_view = &self.scope.borrow().units_view;
+            start.convert_to_unit(&unit, units_view, &range_expr.start.get_range(), self.errors, self.globals);
+            end.convert_to_unit(&unit, units_view, &range_expr.end.get_range(), self.errors, self.globals);
+            step.convert_to_unit(&unit, units_view, &range_expr.get_range(), self.errors, self.globals);
+        }
+        let (start_val, end_val, step_val) = (start.to_double(), end.to_double(), step.to_double());
+        let steps = (end_val - start_val) / step_val;
+        if step_val == 0.0 || steps < 0.0 || steps.is_nan() {
+            return self.add_error_value(errors::inv_range("the end can't be reached with this step.", range_expr.get_range()));
+        }
+        let count = (steps + 1e-9).floor() as usize + 1; //allow for rounding errors, like in 0...1 step 0.1
+        if count > MAX_RANGE_SIZE {
+            let info = format!("the range has more than {} values.", MAX_RANGE_SIZE);
+            return self.add_error_value(errors::inv_range(&info, range_expr.get_range()));
+        }
+        let values = (0..count)
+            .map(|i| {
+                let number = Number { significand: start_val + i as f64 * step_val, exponent: 0, unit: unit.clone(), fmt: start.fmt.clone() };
+                Value::from_number(number, range_expr.get_range())
+            })
+            .collect();
+        Value::from_list(values, range_expr.get_range())
+    }
+
+    fn resolve_date_range(&mut self, start: Timepoint, end: Timepoint, step: Option<Value>, range_expr: &RangeExpr) -> Value {
+        let step = match step {
+            None => Duration::from_days(1),
+            Some(Value { variant: Variant::Duration { duration }, .. }) => duration,
+            Some(Value { variant: Variant::Numeric { number }, .. }) => Duration::from_number(&number, &range_expr.get_range(), self.errors),
+            Some(value) => {
+                let info = format!("the step should be a duration, but found `{}`.", value.variant.name());
+                return self.add_error_value(errors::inv_range(&info, range_expr.get_range()));
+            }
+        };
+        if step.to_days() <= 0 || start.compare(&end) == std::cmp::Ordering::Greater {
+            return self.add_error_value(errors::inv_range("the end can't be reached with this step.", range_expr.get_range()));
+        }
+        let mut values = Vec::new();
+        loop {
+            //always add a multiple of the step to the start, to avoid drifting days when adding months.
+            let date = &start + &(step * &Number::from(values.len() as f64));
+            if date.compare(&end) == std::cmp::Ordering::Greater {
+                break;
+            }
+            if values.len() == MAX_RANGE_SIZE {
+                let info = format!("the range has more than {} values.", MAX_RANGE_SIZE);
+                return self.add_error_value(errors::inv_range(&info, range_expr.get_range()));
+            }
+            values.push(Value::from_date(date, range_expr.get_range()));
+        }
+        Value::from_list(values, range_expr.get_range())
+    }
+
+    fn resolve_index_expr(&mut self, index_expr: &IndexExpr, unit: &Unit) -> Value {
+        let list = self.resolve_node(&index_expr.node);
+        if list.has_errors {
+            return Value::error(index_expr.get_range());
+        }
+        let Variant::List { mut values } = list.variant else {
+            let info = format!("can't index a value of type `{}`.", list.variant.name());
+            return self.add_error_value(errors::inv_index(&info, index_expr.node.get_range()));
+        };
+        let size = values.len() as i64;
+        let result = match &index_expr.index {
+            IndexType::Single(index) => {
+                let Some(i) = self.resolve_list_position(index, values.len()) else {
+                    return Value::error(index_expr.get_range());
+                };
+                values.swap_remove(i)
+            },
+            IndexType::Slice { start, end } => {
+                //like python: slice indices are clipped to the list.
+                let mut bounds = [0, size];
+                for (bound, node) in bounds.iter_mut().zip([start, end]) {
+                    let Some(node) = node else { continue; };
+                    let Some(i) = self.resolve_list_index(node) else {
+                        return Value::error(index_expr.get_range());
+                    };
+                    *bound = if i < 0 { size + i } else { i }.clamp(0, size);
+                }
+                let [start, end] = bounds;
+                let values = if start < end { values.drain(start as usize..end as usize).collect() } else { Vec::new() };
+                Value::from_list(values, index_expr.get_range())
+            }
+        };
+        Resolver::apply_unit(result, unit, &self.scope.borrow().units_view, &index_expr.get_range(), self.errors, self.globals)
+    }
+
+    ///Position of a single index in a list of `size` elements. A negative index counts from the end.
+    fn resolve_list_position(&mut self, node: &Box<Node>, size: usize) -> Option<usize> {
+        let i = self.resolve_list_index(node)?;
+        let size = size as i64;
+        let i_from_start = if i < 0 { size + i } else { i };
+        if i_from_start < 0 || i_from_start >= size {
+            self.errors.push(errors::index_out_of_range(&i.to_string(), &size.to_string(), node.get_range()));
+            return None;
+        }
+        Some(i_from_start as usize)
+    }
+
+    fn resolve_list_index(&mut self, node: &Box<Node>) -> Option<i64> {
+        let value = self.resolve_node(node);
+        if value.has_errors {
+            return None;
+        }
+        match value.as_number() {
+            Some(number) if number.is_int() && number.unit.is_empty() => Some(number.to_double() as i64),
+            _ => {
+                self.errors.push(errors::inv_index("an index should be an integer number without a unit.", node.get_range()));
+                None
+            }
         }
     }
 
@@ -255,36 +550,93 @@ impl<'g, 'a> Resolver<'g, 'a> {
     }
 
     fn resolve_comment_expr(&mut self, comment_expr: &CommentExpr) -> Value {
+        self.echo_results.clear();
+        let mut text = String::new();
+        for part in &comment_expr.parts {
+            match part {
+                EchoPart::Text(range) => text += self.globals.get_text(range),
+                EchoPart::Expr { node, echo_code } => {
+                    let value = self.resolve_node(node);
+                    if *echo_code {
+                        text += self.globals.get_text(&node.get_range());
+                    } else {
+                        text += &self.echo_text(&value);
+                    }
+                    self.echo_results.push(value);
+                },
+                EchoPart::Result(result_ref) => {
+                    let value = self.resolve_result_ref_expr(result_ref, &Unit::none());
+                    text += &self.echo_text(&value);
+                },
+            }
+        }
+        self.echo_results.clear();
         Value {
             id: None,
             stmt_range: comment_expr.get_range().clone(),
-            variant: Variant::Comment,
+            variant: Variant::Comment { text },
             has_errors: false,
+            echo: None,
         }
     }
 
+    fn echo_text(&self, value: &Value) -> String {
+        to_text(value, self.scope.borrow().precision).unwrap_or_else(|| "?".to_string())
+    }
+
     fn resolve_func_def_expr(&mut self, function_def_expr: &FunctionDefExpr) -> Value {
-        if self.scope.borrow().function_accessible(&function_def_expr.id) {
+        //an overload with another number of params doesn't override, but global functions can't be overloaded.
+        let is_override = self.scope.borrow().function_view.ids.contains(&function_def_expr.id)
+            || (self.scope.borrow().function_accessible(&function_def_expr.name) && self.globals.global_function_defs.contains_key(&function_def_expr.name));
+        if is_override {
             if self.scope.borrow().strict {
-                return self.add_error_value(errors::function_override(&function_def_expr.id, function_def_expr.get_range()));
+                return self.add_error_value(errors::function_override(&function_def_expr.name, function_def_expr.get_range()));
             } else {
-                self.errors.push(errors::w_function_override(&function_def_expr.id, function_def_expr.get_range()));
+                self.errors.push(errors::w_function_override(&function_def_expr.name, function_def_expr.get_range()));
             }
         } else {
+            self.scope.borrow_mut().function_view.ids.insert(function_def_expr.name.clone());
             self.scope.borrow_mut().function_view.ids.insert(function_def_expr.id.clone());
         }
+        self.copy_defines_to_body(&function_def_expr.id);
 
         Value {
             id: Some(function_def_expr.id_range.clone()),
             has_errors: false,
             stmt_range: function_def_expr.get_range(),
-            variant: Variant::FunctionDef
+            variant: Variant::FunctionDef,
+            echo: None,
         }
     }
 
 
+    ///The body scope was copied before the #defines were resolved. It gets those of the scope where the function is defined, not those of its callers.
+    fn copy_defines_to_body(&self, function_id: &str) {
+        let Some(body_scope) = Scope::function_body_scope(&self.scope, function_id) else { return; };
+        let scope = self.scope.borrow();
+        let mut body_scope = body_scope.borrow_mut();
+        body_scope.max_recursion = scope.max_recursion;
+        body_scope.physics = scope.physics;
+        body_scope.electric = scope.electric;
+    }
+
+    fn resolve_lambda_expr(&mut self, lambda_expr: &LambdaExpr) -> Value {
+        self.copy_defines_to_body(&lambda_expr.function_name);
+        let captures = self.scope.borrow().frame_variables();
+        Value::from_function_ref(lambda_expr.function_name.clone(), &self.scope, captures, lambda_expr.get_range())
+    }
+
     fn resolve_call_expr(&mut self, call_expr: &CallExpr, unit: &Unit) -> Value {
         let function_name = call_expr.function_name.as_str();
+        //a variable that is assigned a function or a lambda: `f(3)`.
+        let variable = self.scope.borrow().get_variable(function_name);
+        if let Some(function_ref @ Value { variant: Variant::FunctionRef { .. }, .. }) = variable {
+            let Some(arg_values) = self.resolve_arguments(call_expr) else {
+                return Value::error(call_expr.get_range());
+            };
+            let result = call_function_ref(&function_ref, &arg_values, &call_expr.function_name_range, self.errors, self.globals);
+            return Resolver::apply_unit(result, unit, &self.scope.borrow().units_view, &call_expr.get_range(), self.errors, self.globals);
+        }
         if self.scope.borrow().function_accessible(function_name) == false {
             let error = if self.scope.borrow().function_exists(function_name, self.globals) == false {
                 errors::func_not_def(&call_expr.function_name, call_expr.function_name_range.clone())
@@ -294,17 +646,27 @@ impl<'g, 'a> Resolver<'g, 'a> {
             return self.add_error_value(error);
         }
 
-        //resolve the arguments.
-        let mut arg_values: Vec<Value> = Vec::new();
-        for arg in &call_expr.arguments {
-            let value = self.resolve_node(arg);
-            if value.has_errors {
-                return Value::error(call_expr.get_range());
-            }
-            arg_values.push(value);
+        //`solve(equation, x, guess)` doesn't resolve the equation up front, but for every value that is tried for x.
+        if let Some(result) = self.resolve_equation_solve(call_expr) {
+            return Resolver::apply_unit(result, unit, &self.scope.borrow().units_view, &call_expr.get_range(), self.errors, self.globals);
+        }
+        if let Some(result) = self.resolve_symbolic_call(call_expr) {
+            return result;
+        }
+
+        let Some(arg_values) = self.resolve_arguments(call_expr) else {
+            return Value::error(call_expr.get_range());
         };
 
-        let Some(result) = self.scope.borrow().with_function(function_name, self.globals,|fd| {
+        let overload_id = self.scope.borrow().overload_id(function_name, &arg_values);
+        let function_id = match overload_id {
+            Ok(function_id) => function_id,
+            Err(arities) => return self.add_error_value(errors::func_no_overload(function_name, &arities, call_expr.function_name_range.clone())),
+        };
+        //an assertion reports its own failure, for the whole call.
+        let is_assertion = ASSERT_FUNCTIONS.contains(&function_name);
+        let call_range = if is_assertion { call_expr.get_range() } else { call_expr.function_name_range.clone() };
+        let Some(result) = Scope::with_function(&self.scope, &function_id, self.globals,|fd| {
             let mut args_ref = &arg_values;
             let mut exploded_args = Vec::new();
             if fd.get_min_args() > 1 {
@@ -315,9 +677,13 @@ impl<'g, 'a> Resolver<'g, 'a> {
                 return Err(errors::func_arg_wrong(&call_expr.function_name, call_expr.function_name_range.clone()));
             };
             let real_error_count = count_real_errors(self.errors.iter());
-            let result = fd.call(&self.scope.clone(), args_ref,  &call_expr.function_name_range, &mut self.errors, self.globals);
-            if real_error_count != count_real_errors(self.errors.iter()) {
-                self.errors.push(errors::func_has_errors(fd.get_name(), call_expr.function_name_range.clone()));
+            let result = fd.call(&self.scope.clone(), args_ref,  &call_range, &mut self.errors, self.globals);
+            if !is_assertion && real_error_count != count_real_errors(self.errors.iter()) {
+                let error = errors::func_has_errors(fd.get_name(), call_expr.function_name_range.clone());
+                //a recursive call reports the same error at every level.
+                if !self.errors.last().is_some_and(|last| last.id == error.id && last.message == error.message) {
+                    self.errors.push(error);
+                }
             }
             if let Some(parser_errors) = fd.get_parser_errors() {
                 if has_real_errors(parser_errors) {
@@ -335,6 +701,19 @@ impl<'g, 'a> Resolver<'g, 'a> {
         })
     }
 
+    ///None if one of the arguments has errors.
+    fn resolve_arguments(&mut self, call_expr: &CallExpr) -> Option<Vec<Value>> {
+        let mut arg_values: Vec<Value> = Vec::new();
+        for arg in &call_expr.arguments {
+            let value = self.resolve_node(arg);
+            if value.has_errors {
+                return None;
+            }
+            arg_values.push(value);
+        };
+        Some(arg_values)
+    }
+
     fn resolve_unit_expr(&mut self, unit_expr: &UnitExpr, unit: &Unit) -> Value {
         let mut result = self.resolve_node(&unit_expr.node);
         if let Numeric { .. } = &mut result.variant {
@@ -348,36 +727,51 @@ impl<'g, 'a> Resolver<'g, 'a> {
     fn resolve_postfix_expr(&mut self, postfix_expr: &PostfixExpr, unit: &Unit) -> Value {
         let result = self.resolve_node(&postfix_expr.node);
         let id = self.globals.get_text(&postfix_expr.postfix_id.range).to_string();
-        let result = match id.as_str() {
-            "to_days" | "days" | "months" | "years" => self.resolve_duration_fragment(result, &id, &postfix_expr.postfix_id.range),
-            "day" | "month" | "year" => self.resolve_date_fragment(&postfix_expr, result, &id),
-            "bin" | "hex" | "dec" | "oct" | "exp" =>  self.resolve_num_format(postfix_expr, result, &id),
-            _ => self.resolve_unit_postfix(result, &postfix_expr, &id)
+        let result = if let Variant::Record { fields } = &result.variant {
+            self.resolve_record_field(fields, &postfix_expr.postfix_id, &id)
+        } else {
+            match id.as_str() {
+                "to_days" | "days" | "months" | "years" => self.resolve_duration_fragment(result, &id, &postfix_expr.postfix_id.range),
+                "day" | "month" | "year" => self.resolve_date_fragment(&postfix_expr, result, &id),
+                "bin" | "hex" | "dec" | "oct" | "exp" =>  self.resolve_num_format(postfix_expr, result, &id),
+                "rect" | "polar" => self.resolve_complex_format(postfix_expr, result, &id),
+                _ => self.resolve_unit_postfix(result, &postfix_expr.postfix_id, &id)
+            }
         };
 
         Resolver::apply_unit(result, unit, &self.scope.borrow().units_view, &postfix_expr.get_range(), self.errors, self.globals)
     }
 
-    fn resolve_unit_postfix(&mut self, mut result: Value, pfix_expr: &PostfixExpr, id: &String) -> Value {
+    ///Field access comes before a unit conversion: `p.m` is the field `m` of record `p`.
+    fn resolve_record_field(&mut self, fields: &[(String, Value)], postfix_id: &Token, id: &str) -> Value {
+        let Some((_, value)) = fields.iter().find(|(name, _)| name == id) else {
+            return self.add_error_value(errors::field_not_def(id, postfix_id.range.clone()));
+        };
+        let mut value = value.clone();
+        value.id = None;
+        value
+    }
+
+    fn resolve_unit_postfix(&mut self, mut result: Value, postfix_id: &Token, id: &String) -> Value {
         match &mut result.variant {
             Numeric { ref mut number, .. } => {
-                if pfix_expr.postfix_id.kind == TokenType::ClearUnit {
+                if postfix_id.kind == TokenType::ClearUnit {
                     number.unit = Unit::none();
                 } else {
-                    let unit = if let Some(var) = self.scope.borrow().variables.get(id) {
+                    let unit = if let Some(var) = self.scope.borrow().get_variable(id) {
                         var.as_number().map_or(Unit::none(), |number| number.unit.clone())
                     } else {
-                        if let Some(constant) = self.globals.constants.get(id.as_str()) {
-                            constant.unit.clone()
+                        if let Some(constant) = self.scope.borrow().get_constant(id, self.globals) {
+                            constant.unit
                         } else {
-                            Unit::from_id(&id, Some(pfix_expr.postfix_id.range.clone()))
+                            Unit::from_id(&id, Some(postfix_id.range.clone()))
                         }
                     };
-                    number.convert_to_unit(&unit, &self.scope.borrow().units_view, &pfix_expr.postfix_id.range, self.errors, self.globals);
+                    number.convert_to_unit(&unit, &self.scope.borrow().units_view, &postfix_id.range, self.errors, self.globals);
                 }
             },
             _ => {
-                let range = pfix_expr.postfix_id.range.clone();
+                let range = postfix_id.range.clone();
                 return self.return_error(errors::unknown_expr("Postfix expression not valid here.", range), result);
             }
         };
@@ -399,6 +793,16 @@ impl<'g, 'a> Resolver<'g, 'a> {
         result
     }
 
+    ///A real number remains a number: it has no other notation.
+    fn resolve_complex_format(&mut self, pfix_expr: &PostfixExpr, mut result: Value, id: &str) -> Value {
+        match &mut result.variant {
+            Variant::Complex { complex } => complex.fmt = if id == "polar" { ComplexFormat::Polar } else { ComplexFormat::Rect },
+            Numeric { .. } => {},
+            _ => return self.return_error(errors::inv_format(id, pfix_expr.postfix_id.range.clone()), result),
+        }
+        result
+    }
+
     fn resolve_date_fragment(&mut self, pfix_expr: &PostfixExpr, mut result: Value, id: &str) -> Value {
         let Some(date) = result.as_date() else {
             return self.return_error(errors::inv_format(id, pfix_expr.postfix_id.range.clone()), result);
@@ -421,6 +825,7 @@ impl<'g, 'a> Resolver<'g, 'a> {
                 },
             },
             has_errors: false,
+            echo: None,
         }
     }
 
@@ -449,45 +854,228 @@ impl<'g, 'a> Resolver<'g, 'a> {
     }
 
     fn resolve_assign_expr(&mut self, assign_expr: &AssignExpr) -> Value {
-        let mut value = self.resolve_node(&assign_expr.expr);
-        let id_str = self.globals.get_text(&assign_expr.assignable.id.range).to_string();
+        let value = match assign_expr.op.kind {
+            TokenType::Eq => self.resolve_node(&assign_expr.expr),
+            TokenType::EqUnit => {
+                let current = self.resolve_node(&assign_expr.assignable);
+                let NodeType::Id(unit_id) = &assign_expr.expr.expr else { unreachable!("EqUnit expects an id.") };
+                let id = self.globals.get_text(&unit_id.id.range).to_string();
+                self.resolve_unit_postfix(current, &unit_id.id, &id)
+            },
+            _ => {
+                let current = self.resolve_node(&assign_expr.assignable);
+                let expr = self.resolve_node(&assign_expr.expr);
+                if current.has_errors || expr.has_errors {
+                    return Value::error(assign_expr.get_range());
+                }
+                self.resolve_operation(current, &assign_expr.op, expr, &assign_expr.get_range())
+            }
+        };
+        self.assign_to(&assign_expr.assignable, value)
+    }
+
+    fn resolve_const_def_expr(&mut self, const_def_expr: &ConstDefExpr) -> Value {
+        let value = self.resolve_node(&const_def_expr.assign);
+        if let (Some(id_range), false) = (&value.id, value.has_errors) {
+            let id = self.globals.get_text(id_range).to_string();
+            self.scope.borrow_mut().const_defs.insert(id);
+        }
+        value
+    }
+
+    fn is_constant(&self, id: &str) -> bool {
+        self.scope.borrow().is_const_def(id) || self.scope.borrow().get_constant(id, self.globals).is_some()
+    }
+
+    ///Stores the value in the assignable. Parts of a variable (list items, date fragments,...) are assigned by
+    ///creating a modified copy of the variable and assigning that copy to the variable.
+    ///Returns the value of the (whole) variable.
+    fn assign_to(&mut self, assignable: &Box<Node>, value: Value) -> Value {
+        if !assignable.unit.is_empty() {
+            return self.add_error_value(errors::not_assignable(self.globals.get_text(&assignable.get_range()), assignable.get_range()));
+        }
+        match &assignable.expr {
+            NodeType::Id(id_expr) if id_expr.selector.is_none() => self.assign_to_variable(&id_expr.id.range, value),
+            NodeType::ResultRef(result_ref) if result_ref.implicit => { // `+=40;`
+                let Some(id_range) = self.last_result.as_ref().and_then(|last| last.id.clone()) else {
+                    return self.add_error_value(errors::no_last_var(result_ref.get_range()));
+                };
+                self.assign_to_variable(&id_range, value)
+            },
+            NodeType::Index(index_expr) => {
+                let IndexType::Single(index) = &index_expr.index else {
+                    return self.add_error_value(errors::not_assignable(self.globals.get_text(&index_expr.get_range()), index_expr.get_range()));
+                };
+                let mut list = self.resolve_node(&index_expr.node);
+                if list.has_errors || value.has_errors {
+                    return Value::error(index_expr.get_range());
+                }
+                let Variant::List { ref mut values } = list.variant else {
+                    let info = format!("can't index a value of type `{}`.", list.variant.name());
+                    return self.add_error_value(errors::inv_index(&info, index_expr.node.get_range()));
+                };
+                let Some(i) = self.resolve_list_position(index, values.len()) else {
+                    return Value::error(index_expr.get_range());
+                };
+                values[i] = value;
+                self.assign_to(&index_expr.node, list)
+            },
+            NodeType::Postfix(postfix_expr) => {
+                let mut container = self.resolve_node(&postfix_expr.node);
+                if container.has_errors || value.has_errors {
+                    return Value::error(postfix_expr.get_range());
+                }
+                let id = self.globals.get_text(&postfix_expr.postfix_id.range);
+                //a field that doesn't exist yet is added, as for variables.
+                if let Variant::Record { ref mut fields } = container.variant {
+                    match fields.iter_mut().find(|(name, _)| name == id) {
+                        Some((_, field)) => *field = value,
+                        None => fields.push((id.to_string(), value)),
+                    }
+                    return self.assign_to(&postfix_expr.node, container);
+                }
+                let container = match id {
+                    "day" | "month" | "year" => self.assign_date_fragment(container, id, value, &postfix_expr.postfix_id.range),
+                    "days" | "months" | "years" => self.assign_duration_fragment(container, id, value, &postfix_expr.postfix_id.range),
+                    _ => return self.add_error_value(errors::not_assignable(self.globals.get_text(&postfix_expr.get_range()), postfix_expr.get_range())),
+                };
+                if container.has_errors {
+                    return container;
+                }
+                self.assign_to(&postfix_expr.node, container)
+            },
+            _ => {
+                let range = self.globals.close_parentheses(&assignable.get_range());
+                self.add_error_value(errors::not_assignable(self.globals.get_text(&range), range))
+            }
+        }
+    }
+
+    fn assign_date_fragment(&mut self, mut container: Value, fragment: &str, value: Value, range: &Range) -> Value {
+        let Variant::Date { ref mut date } = container.variant else {
+            return self.add_error_value(errors::date_frag_no_date(fragment, range.clone()));
+        };
+        let Some(n) = self.fragment_value(&value, fragment, range) else {
+            return Value::error(range.clone());
+        };
+        match fragment {
+            "day" if (1..=31).contains(&n) => date.day = Day::Value(n as i8),
+            "month" if (1..=12).contains(&n) => date.month = month_from_int(n),
+            "year" => date.year = Some(n),
+            _ => return self.add_error_value(errors::inv_date_value(&n.to_string(), fragment, value.stmt_range.clone())),
+        }
+        if !date.is_valid() {
+            return self.add_error_value(errors::inv_date(range.clone()));
+        }
+        container
+    }
+
+    fn assign_duration_fragment(&mut self, mut container: Value, fragment: &str, value: Value, range: &Range) -> Value {
+        let Variant::Duration { ref mut duration } = container.variant else {
+            return self.add_error_value(errors::date_frag_no_duration(fragment, range.clone()));
+        };
+        let Some(n) = self.fragment_value(&value, fragment, range) else {
+            return Value::error(range.clone());
+        };
+        duration.normalize(); //fragments are read from a normalized duration.
+        match fragment {
+            "days" => duration.days = n,
+            "months" => duration.months = n,
+            _ => duration.years = n,
+        }
+        container
+    }
+
+    fn fragment_value(&mut self, value: &Value, fragment: &str, range: &Range) -> Option<i32> {
+        match value.as_number() {
+            Some(number) if number.is_int() && number.unit.is_empty() => Some(number.to_double() as i32),
+            _ => {
+                let info = format!("`{}` expects an integer number without a unit.", fragment);
+                self.errors.push(errors::value_error(&info, range.clone()));
+                None
+            }
+        }
+    }
+
+    fn assign_to_variable(&mut self, id_range: &Range, mut value: Value) -> Value {
+        let id_str = self.globals.get_text(id_range).to_string();
         if !self.scope.borrow().variables.contains_key(&id_str) {
             if self.scope.borrow().function_exists(&id_str, self.globals) {
-                self.errors.push(errors::w_var_is_function(id_str.as_str(), assign_expr.assignable.id.range.clone()));
+                self.errors.push(errors::w_var_is_function(id_str.as_str(), id_range.clone()));
             }
             if self.scope.borrow().units_view.units.contains(&id_str) {
                 if self.scope.borrow().strict {
-                    return self.add_error_value(errors::var_is_unit(id_str.as_str(), assign_expr.assignable.id.range.clone()));
+                    return self.add_error_value(errors::var_is_unit(id_str.as_str(), id_range.clone()));
                 } else {
-                    self.errors.push(errors::w_var_is_unit(id_str.as_str(), assign_expr.assignable.id.range.clone()));
+                    self.errors.push(errors::w_var_is_unit(id_str.as_str(), id_range.clone()));
                 }
             }
         }
         //disallow redefine of constant in case of `strict`. Error has already been added
-        if self.globals.constants.contains_key(&id_str.as_str()) {
+        if self.is_constant(&id_str) {
             if self.scope.borrow().strict {
-                self.errors.push(errors::const_redef(id_str.as_str(), assign_expr.assignable.id.range.clone()));
+                self.errors.push(errors::const_redef(id_str.as_str(), id_range.clone()));
                 return value;
             } else {
-                self.errors.push(errors::w_const_redef(id_str.as_str(), assign_expr.assignable.id.range.clone()));
+                self.errors.push(errors::w_const_redef(id_str.as_str(), id_range.clone()));
             }
         }
-        self.scope.borrow_mut().variables.insert(id_str.clone(), value.clone());
-        value.id = Some(assign_expr.assignable.id.range.clone()); //add id here to avoid adding id to the self.scope.variables.
+        self.scope.borrow_mut().set_variable(&id_str, value.clone());
+        value.id = Some(id_range.clone()); //add id here to avoid adding id to the self.scope.variables.
         if let Variant::None = value.variant {
-            self.errors.push(errors::var_no_value(id_str.as_str(), assign_expr.assignable.id.range.clone()));
+            self.errors.push(errors::var_no_value(id_str.as_str(), id_range.clone()));
         }
         value
     }
 
+    fn resolve_result_ref_expr(&mut self, result_ref: &ResultRefExpr, unit: &Unit) -> Value {
+        let result = match result_ref.ref_type {
+            ResultRefType::Last => self.last_result.clone(),
+            ResultRefType::Line(line) => self.line_results.iter().rev()
+                .find(|value| value.stmt_range.source_index == result_ref.token.range.source_index
+                    && self.globals.get_line_and_column(&value.stmt_range).0 + 1 == line)
+                .cloned(),
+            ResultRefType::Echo(Some(index)) => self.echo_results.get(index).cloned(),
+            ResultRefType::Echo(None) => self.echo_results.last().or(self.last_result.as_ref()).cloned(),
+        };
+        let Some(mut result) = result else {
+            let reference = if result_ref.implicit { "_" } else { self.globals.get_text(&result_ref.token.range) }.to_string();
+            return self.add_error_value(errors::no_result(&reference, result_ref.get_range()));
+        };
+        if result.has_errors { //error already reported.
+            return Value::error(result_ref.get_range());
+        }
+        result.id = None;
+        result.echo = None;
+        Resolver::apply_unit(result, unit, &self.scope.borrow().units_view, &result_ref.get_range(), self.errors, self.globals)
+    }
+
     fn resolve_id_expr(&mut self, id_expr: &IdExpr, unit: &Unit) -> Value {
         let id = self.globals.get_text(&id_expr.id.range).to_string();
-        let var_exists = self.scope.borrow().variables.contains_key(&id);
-        let result = if var_exists  {
-            self.scope.borrow().variables[&id].clone()
+        if let Some(selector) = &id_expr.selector {
+            let values = match &selector.selector_type {
+                SelectorType::NumericSuffix => self.scope.borrow().select_variables(|var| {
+                    var.strip_prefix(id.as_str()).is_some_and(|suffix| !suffix.is_empty() && suffix.chars().all(|c| c.is_ascii_digit()))
+                }),
+                SelectorType::Prefix => self.scope.borrow().select_variables(|var| var.starts_with(id.as_str())),
+                SelectorType::Range { last } => {
+                    let first = natural_key(&id);
+                    let last = natural_key(self.globals.get_text(&last.range));
+                    self.scope.borrow().select_variables(|var| (first..=last).contains(&natural_key(var)))
+                },
+            };
+            let result = Value::from_list(values, id_expr.get_range());
+            return Resolver::apply_unit(result, unit, &self.scope.borrow().units_view, &id_expr.get_range(), self.errors, self.globals);
+        }
+        let variable = self.scope.borrow().get_variable(&id);
+        let result = if let Some(variable) = variable {
+            variable
         } else {
-            if self.globals.constants.contains_key(id.as_str()) {
-                Value::from_number(self.globals.constants[&id as &str].clone(), id_expr.get_range())
+            let constant = self.scope.borrow().get_constant(&id, self.globals);
+            if let Some(constant) = constant {
+                Value::from_number(constant, id_expr.get_range())
+            } else if self.scope.borrow().function_accessible(&id) {
+                Value::from_function_ref(id.clone(), &self.scope, HashMap::new(), id_expr.get_range())
             } else {
                 self.add_error_value(errors::var_not_def(&id, id_expr.id.range.clone()))
             }
@@ -500,6 +1088,8 @@ impl<'g, 'a> Resolver<'g, 'a> {
         if unary_expr.op.kind == TokenType::Min {
             if let Numeric {ref mut number,..} = result.variant {
                 number.significand = -number.significand
+            } else if let Variant::Complex { ref mut complex } = result.variant {
+                *complex = -&*complex;
             }
         }
         result
@@ -508,6 +1098,11 @@ impl<'g, 'a> Resolver<'g, 'a> {
     fn resolve_const_expr(&mut self, const_expr: &ConstExpr, unit: &Unit) -> Value {
         match &const_expr.const_type {
             ConstType::Numeric { number } => {
+                //an imaginary literal, as in `4i`, unless there's a unit with that id.
+                let imaginary_unit = self.scope.borrow().imaginary_unit();
+                if unit.id.len() == 1 && unit.id.starts_with(imaginary_unit) && self.scope.borrow().units_view.get_def(&unit.id, self.globals).is_none() {
+                    return Value::from_complex(Complex::new(0.0, number.to_double(), imaginary_unit), const_expr.get_range());
+                }
                 let mut n = number.clone();
                 n.unit = unit.clone();
                 let res = Value::from_number(n, const_expr.get_range());
@@ -534,32 +1129,83 @@ impl<'g, 'a> Resolver<'g, 'a> {
                     self.errors.push(num_error);
                 }
                 Value::from_date(date, const_expr.get_range())
-            }
+            },
+            ConstType::Text => Value::from_text(self.globals.get_text(&const_expr.range).to_string(), const_expr.get_range()),
         }
     }
 
     fn resolve_bin_expr(&mut self, bin_expr: &BinExpr, unit: &Unit) -> Value {
 
         let error_cnt_before = self.errors.len();
-        let expr1 = self.resolve_node(&bin_expr.expr1);
-        let expr2 = self.resolve_node(&bin_expr.expr2);
+        let mut expr1 = self.resolve_node(&bin_expr.expr1);
+        let mut expr2 = self.resolve_node(&bin_expr.expr2);
+        //operators report errors on the operands, not on the variables they came from.
+        expr1.stmt_range = bin_expr.expr1.get_range();
+        expr2.stmt_range = bin_expr.expr2.get_range();
         if error_cnt_before != self.errors.len() {
             if has_real_errors(&self.errors[error_cnt_before..]) {
                 return Value::error(bin_expr.get_range());
             }
         }
 
-        let operator_type = OperatorType::from(&bin_expr.op.kind);
-        let op_id = operator_id_from(expr1.variant.to_operand_type(), operator_type, expr2.variant.to_operand_type());
+        //`f(1, 2)` where the parser didn't know that f is a function, as for a parameter: the list holds the arguments.
+        //Also within parentheses, which turn the implicit mult into a regular one: `(f(x)) + 1`.
+        let is_call = matches!(expr1.variant, Variant::FunctionRef {..}) && self.globals.get_text(&bin_expr.op.range) == "(";
+        let result = if is_call {
+            let args = match (&bin_expr.expr2.expr, expr2.variant) {
+                (NodeType::List(_), Variant::List { values }) => values,
+                (NodeType::None(_), _) => Vec::new(),
+                (_, variant) => vec![Value { variant, ..expr2 }],
+            };
+            call_function_ref(&expr1, &args, &bin_expr.expr1.get_range(), self.errors, self.globals)
+        } else {
+            self.resolve_operation(expr1, &bin_expr.op, expr2, &bin_expr.get_range())
+        };
+        if bin_expr.implicit_mult {
+            if let NodeType::Id(id_expr) = &bin_expr.expr2.expr {
+                let id_str = self.globals.get_text(&id_expr.id.range);
+                if self.scope.borrow().units_view.units.contains(id_str) {
+                    self.add_error_value(errors::w_unit_is_var(id_str, id_expr.id.range.clone()));
+                }
+            }
+        }
+        Resolver::apply_unit(result, unit, &self.scope.borrow().units_view, &bin_expr.get_range(), self.errors, self.globals)
+    }
+
+    ///`a ~= b within tolerance`. Without a tolerance, the precision of the scope is used.
+    fn resolve_approx_expr(&mut self, approx_expr: &ApproxExpr, unit: &Unit) -> Value {
+        let expr1 = self.resolve_node(&approx_expr.expr1);
+        let expr2 = self.resolve_node(&approx_expr.expr2);
+        let tolerance = match &approx_expr.tolerance {
+            Some(tolerance) => self.resolve_node(tolerance),
+            None => Value::from_number(Number::from(1.0 / self.scope.borrow().precision), approx_expr.get_range()),
+        };
+        if expr1.has_errors || expr2.has_errors || tolerance.has_errors {
+            return Value::error(approx_expr.get_range());
+        }
+        if !matches!(tolerance.variant, Variant::Numeric {..}) {
+            return self.add_error_value(errors::expected_numeric_value(tolerance.stmt_range.clone()));
+        }
+        let result = self.apply_operator(OperatorType::from(&approx_expr.op.kind), vec![expr1, expr2, tolerance], &approx_expr.get_range());
+        Resolver::apply_unit(result, unit, &self.scope.borrow().units_view, &approx_expr.get_range(), self.errors, self.globals)
+    }
+
+    ///Applies the operator (or the operator of a compound assignment like `+=`) to the values.
+    fn resolve_operation(&mut self, expr1: Value, op: &Token, expr2: Value, expr_range: &Range) -> Value {
+        self.apply_operator(OperatorType::from(&op.kind), vec![expr1, expr2], expr_range)
+    }
+
+    ///The operator is looked up by the type of the first two arguments.
+    fn apply_operator(&mut self, operator_type: OperatorType, args: Vec<Value>, expr_range: &Range) -> Value {
+        let op_id = operator_id_from(args[0].variant.to_operand_type(), operator_type, args[1].variant.to_operand_type());
         if !self.globals.exists_operator(op_id) {
             let op_str = operator_type.to_string();
-            let val_type1 = &expr1.variant.name();
-            let val_type2 = &expr2.variant.name();
-            return self.add_error_value(errors::no_op(&op_str, &val_type1, &val_type2, bin_expr.get_range().clone()));
+            let val_type1 = &args[0].variant.name();
+            let val_type2 = &args[1].variant.name();
+            return self.add_error_value(errors::no_op(&op_str, &val_type1, &val_type2, expr_range.clone()));
         }
 
-        let args = vec![expr1, expr2];
-        let range = Range { source_index: bin_expr.get_range().source_index, start: 0, end: 0};
+        let range = Range { source_index: expr_range.source_index, start: 0, end: 0};
 
         let mut result = (self.globals.get_operator(op_id).unwrap())(&self.globals, &args, &range, &mut self.errors); //unwrap: op_id already checked.
         if result.has_errors {
@@ -570,15 +1216,7 @@ impl<'g, 'a> Resolver<'g, 'a> {
                 self.errors.append(&mut date.errors.clone());
             }
         }
-        if bin_expr.implicit_mult {
-            if let NodeType::Id(id_expr) = &bin_expr.expr2.expr {
-                let id_str = self.globals.get_text(&id_expr.id.range);
-                if self.scope.borrow().units_view.units.contains(id_str) {
-                    self.add_error_value(errors::w_unit_is_var(id_str, id_expr.id.range.clone()));
-                }
-            }
-        }
-        Resolver::apply_unit(result, unit, &self.scope.borrow().units_view, &bin_expr.get_range(), self.errors, self.globals)
+        result
     }
 }
 
@@ -594,3 +1232,9 @@ pub fn explode_if_one_arg<'a>(args: &'a Vec<Value>, exploded_args: &'a mut Vec<V
         args
     }
 }
+
+///Splits a name in its text and a numeric suffix, so that `item2` sorts before `item10`.
+fn natural_key(name: &str) -> (&str, Option<u64>) {
+    let text = name.trim_end_matches(|c: char| c.is_ascii_digit());
+    (text, name[text.len()..].parse().ok())
+}
@@ -1,7 +1,10 @@
+use std::cmp::Ordering;
 use std::fmt::{Display, Formatter};
+use crate::complex::Complex;
 use crate::errors;
 use crate::errors::Error;
 use crate::globals::Globals;
+use crate::matrix::Matrix;
 use crate::resolver::unit::Unit;
 use crate::resolver::value::{OperandType, Value, Variant};
 use crate::tokenizer::cursor::Range;
@@ -18,7 +21,14 @@ pub enum OperatorType {
     Div = 4,
     Power = 5,
     Remain = 6,
-    Modulo = 7
+    Modulo = 7,
+    Less = 8,
+    Greater = 9,
+    LessEq = 10,
+    GreaterEq = 11,
+    Equal = 12,
+    NotEqual = 13,
+    Approx = 14,
 }
 
 impl Display for OperatorType {
@@ -31,6 +41,13 @@ impl Display for OperatorType {
             OperatorType::Power => write!(f, "^"),
             OperatorType::Remain => write!(f, "%"),
             OperatorType::Modulo => write!(f, "%%"),
+            OperatorType::Less => write!(f, "<"),
+            OperatorType::Greater => write!(f, ">"),
+            OperatorType::LessEq => write!(f, "<="),
+            OperatorType::GreaterEq => write!(f, ">="),
+            OperatorType::Equal => write!(f, "=="),
+            OperatorType::NotEqual => write!(f, "!="),
+            OperatorType::Approx => write!(f, "~="),
         }
     }
 }
@@ -38,18 +55,59 @@ impl Display for OperatorType {
 impl From<&TokenType> for OperatorType {
     fn from(value: &TokenType) -> Self {
         match value {
-            TokenType::Plus => OperatorType::Plus,
-            TokenType::Min => OperatorType::Min,
-            TokenType::Mult => OperatorType::Mult,
-            TokenType::Div => OperatorType::Div,
+            TokenType::Plus | TokenType::EqPlus => OperatorType::Plus,
+            TokenType::Min | TokenType::EqMin => OperatorType::Min,
+            TokenType::Mult | TokenType::EqMult => OperatorType::Mult,
+            TokenType::Div | TokenType::EqDiv => OperatorType::Div,
             TokenType::Power => OperatorType::Power,
             TokenType::Percent => OperatorType::Remain,
             TokenType::Modulo=> OperatorType::Modulo,
+            TokenType::Less => OperatorType::Less,
+            TokenType::Greater => OperatorType::Greater,
+            TokenType::LessEq => OperatorType::LessEq,
+            TokenType::GreaterEq => OperatorType::GreaterEq,
+            TokenType::EqEq => OperatorType::Equal,
+            TokenType::NotEq => OperatorType::NotEqual,
+            TokenType::Approx => OperatorType::Approx,
             _ => unreachable!("This is not an operator!")
         }
     }
 }
 
+impl OperatorType {
+    fn from_u8(op: u8) -> OperatorType {
+        match op {
+            1 => OperatorType::Plus,
+            2 => OperatorType::Min,
+            3 => OperatorType::Mult,
+            4 => OperatorType::Div,
+            5 => OperatorType::Power,
+            6 => OperatorType::Remain,
+            7 => OperatorType::Modulo,
+            8 => OperatorType::Less,
+            9 => OperatorType::Greater,
+            10 => OperatorType::LessEq,
+            11 => OperatorType::GreaterEq,
+            12 => OperatorType::Equal,
+            13 => OperatorType::NotEqual,
+            14 => OperatorType::Approx,
+            _ => unreachable!("This is not an operator!")
+        }
+    }
+
+    pub fn compare(&self, ordering: Ordering) -> bool {
+        match self {
+            OperatorType::Less => ordering == Ordering::Less,
+            OperatorType::Greater => ordering == Ordering::Greater,
+            OperatorType::LessEq => ordering != Ordering::Greater,
+            OperatorType::GreaterEq => ordering != Ordering::Less,
+            OperatorType::Equal => ordering == Ordering::Equal,
+            OperatorType::NotEqual => ordering != Ordering::Equal,
+            _ => unreachable!("This is not a comparison operator!")
+        }
+    }
+}
+
 pub fn operator_id_from(type1: OperandType, op: OperatorType, type2: OperandType) -> u32 {
     (type1 as u32 *265*265) + (op as u32*265) + type2 as u32
 }
@@ -90,12 +148,22 @@ pub fn op_num_mod_num(_globals: &Globals, args: &Vec<Value>, range: &Range, _err
     Value::from_number(Number { significand: ((n1.to_double() % n2.to_double()) + n2.to_double()) % n2.to_double(), exponent: 0, unit : Unit::none(), fmt: NumberFormat::Dec }, range.clone())
 }
 
-pub fn op_num_pow_num(_globals: &Globals, args: &Vec<Value>, range: &Range, _errors: &mut Vec<Error>) -> Value {
+pub fn op_num_pow_num(_globals: &Globals, args: &Vec<Value>, range: &Range, errors: &mut Vec<Error>) -> Value {
     let Variant::Numeric {number: ref n1, ..} = &args[0].variant else { unreachable!(); };
     let Variant::Numeric {number: ref n2, ..} = &args[1].variant else { unreachable!(); };
+    if n1.to_double() < 0.0 && n2.to_double().fract() != 0.0 {
+        errors.push(errors::value_error("A negative number to a fractional power has no real result.", operands_range(&args[0], &args[1], range)));
+        return Value::error(range.clone());
+    }
     Value::from_number(Number { significand: n1.to_double().powf(n2.to_double()), exponent: 0, unit : Unit::none(), fmt: NumberFormat::Dec }, range.clone())
 }
 
+pub fn op_text_plus_text(_globals: &Globals, args: &Vec<Value>, range: &Range, _errors: &mut Vec<Error>) -> Value {
+    let Variant::Text {text: ref t1} = &args[0].variant else { unreachable!(); };
+    let Variant::Text {text: ref t2} = &args[1].variant else { unreachable!(); };
+    Value::from_text(format!("{}{}", t1, t2), range.clone())
+}
+
 pub fn load_operators(globals: &mut Globals) {
     use OperandType as OT;
     globals.operators.insert(operator_id_from(OT::Number, OperatorType::Plus, OT::Number), op_num_plus_num);
@@ -112,6 +180,286 @@ pub fn load_operators(globals: &mut Globals) {
     globals.operators.insert(operator_id_from(OT::Duration, OperatorType::Plus, OT::Duration), op_dur_plus_dur);
     globals.operators.insert(operator_id_from(OT::Duration, OperatorType::Mult, OT::Number), op_dur_mult_num);
     globals.operators.insert(operator_id_from(OT::Duration, OperatorType::Div, OT::Number), op_dur_div_num);
+
+    globals.operators.insert(operator_id_from(OT::Text, OperatorType::Plus, OT::Text), op_text_plus_text);
+
+    for operand_type in [OT::Number, OT::Date, OT::Duration, OT::Text] {
+        globals.operators.insert(operator_id_from(operand_type, OperatorType::Less, operand_type), op_compare::<{OperatorType::Less as u8}>);
+        globals.operators.insert(operator_id_from(operand_type, OperatorType::Greater, operand_type), op_compare::<{OperatorType::Greater as u8}>);
+        globals.operators.insert(operator_id_from(operand_type, OperatorType::LessEq, operand_type), op_compare::<{OperatorType::LessEq as u8}>);
+        globals.operators.insert(operator_id_from(operand_type, OperatorType::GreaterEq, operand_type), op_compare::<{OperatorType::GreaterEq as u8}>);
+        globals.operators.insert(operator_id_from(operand_type, OperatorType::Equal, operand_type), op_compare::<{OperatorType::Equal as u8}>);
+        globals.operators.insert(operator_id_from(operand_type, OperatorType::NotEqual, operand_type), op_compare::<{OperatorType::NotEqual as u8}>);
+    }
+    globals.operators.insert(operator_id_from(OT::Bool, OperatorType::Equal, OT::Bool), op_compare::<{OperatorType::Equal as u8}>);
+    globals.operators.insert(operator_id_from(OT::Bool, OperatorType::NotEqual, OT::Bool), op_compare::<{OperatorType::NotEqual as u8}>);
+    globals.operators.insert(operator_id_from(OT::Number, OperatorType::Approx, OT::Number), op_num_approx_num);
+
+    for operand_type in [OT::Number, OT::Date, OT::Duration, OT::List, OT::Complex] {
+        globals.operators.insert(operator_id_from(OT::List, OperatorType::Plus, operand_type), op_list::<{OperatorType::Plus as u8}>);
+        globals.operators.insert(operator_id_from(OT::List, OperatorType::Min, operand_type), op_list::<{OperatorType::Min as u8}>);
+        globals.operators.insert(operator_id_from(OT::List, OperatorType::Mult, operand_type), op_list::<{OperatorType::Mult as u8}>);
+        globals.operators.insert(operator_id_from(OT::List, OperatorType::Div, operand_type), op_list::<{OperatorType::Div as u8}>);
+        globals.operators.insert(operator_id_from(OT::List, OperatorType::Power, operand_type), op_list::<{OperatorType::Power as u8}>);
+        globals.operators.insert(operator_id_from(OT::List, OperatorType::Remain, operand_type), op_list::<{OperatorType::Remain as u8}>);
+        globals.operators.insert(operator_id_from(OT::List, OperatorType::Modulo, operand_type), op_list::<{OperatorType::Modulo as u8}>);
+        globals.operators.insert(operator_id_from(operand_type, OperatorType::Plus, OT::List), op_list::<{OperatorType::Plus as u8}>);
+        globals.operators.insert(operator_id_from(operand_type, OperatorType::Min, OT::List), op_list::<{OperatorType::Min as u8}>);
+        globals.operators.insert(operator_id_from(operand_type, OperatorType::Mult, OT::List), op_list::<{OperatorType::Mult as u8}>);
+        globals.operators.insert(operator_id_from(operand_type, OperatorType::Div, OT::List), op_list::<{OperatorType::Div as u8}>);
+        globals.operators.insert(operator_id_from(operand_type, OperatorType::Power, OT::List), op_list::<{OperatorType::Power as u8}>);
+        globals.operators.insert(operator_id_from(operand_type, OperatorType::Remain, OT::List), op_list::<{OperatorType::Remain as u8}>);
+        globals.operators.insert(operator_id_from(operand_type, OperatorType::Modulo, OT::List), op_list::<{OperatorType::Modulo as u8}>);
+    }
+
+    globals.operators.insert(operator_id_from(OT::Matrix, OperatorType::Plus, OT::Matrix), op_matrix_term_matrix::<{OperatorType::Plus as u8}>);
+    globals.operators.insert(operator_id_from(OT::Matrix, OperatorType::Min, OT::Matrix), op_matrix_term_matrix::<{OperatorType::Min as u8}>);
+    globals.operators.insert(operator_id_from(OT::Matrix, OperatorType::Mult, OT::Matrix), op_matrix_mult_matrix);
+    globals.operators.insert(operator_id_from(OT::Matrix, OperatorType::Mult, OT::List), op_matrix_mult_list);
+    globals.operators.insert(operator_id_from(OT::Matrix, OperatorType::Mult, OT::Number), op_matrix_scale::<{OperatorType::Mult as u8}>);
+    globals.operators.insert(operator_id_from(OT::Number, OperatorType::Mult, OT::Matrix), op_matrix_scale::<{OperatorType::Mult as u8}>);
+    globals.operators.insert(operator_id_from(OT::Matrix, OperatorType::Div, OT::Number), op_matrix_scale::<{OperatorType::Div as u8}>);
+
+    for (type1, type2) in [(OT::Complex, OT::Complex), (OT::Complex, OT::Number), (OT::Number, OT::Complex)] {
+        globals.operators.insert(operator_id_from(type1, OperatorType::Plus, type2), op_complex::<{OperatorType::Plus as u8}>);
+        globals.operators.insert(operator_id_from(type1, OperatorType::Min, type2), op_complex::<{OperatorType::Min as u8}>);
+        globals.operators.insert(operator_id_from(type1, OperatorType::Mult, type2), op_complex::<{OperatorType::Mult as u8}>);
+        globals.operators.insert(operator_id_from(type1, OperatorType::Div, type2), op_complex::<{OperatorType::Div as u8}>);
+        globals.operators.insert(operator_id_from(type1, OperatorType::Power, type2), op_complex::<{OperatorType::Power as u8}>);
+    }
+    globals.operators.insert(operator_id_from(OT::Complex, OperatorType::Equal, OT::Complex), op_complex::<{OperatorType::Equal as u8}>);
+    globals.operators.insert(operator_id_from(OT::Complex, OperatorType::NotEqual, OT::Complex), op_complex::<{OperatorType::NotEqual as u8}>);
+}
+
+///A number is used as a complex number without imaginary part, in SI units. The result has the format of the first complex operand.
+pub fn op_complex<const OP: u8>(globals: &Globals, args: &Vec<Value>, range: &Range, errors: &mut Vec<Error>) -> Value {
+    let template = match (&args[0].variant, &args[1].variant) {
+        (Variant::Complex { complex }, _) | (_, Variant::Complex { complex }) => complex,
+        _ => unreachable!()
+    };
+    let to_complex = |value: &Value| match &value.variant {
+        Variant::Complex { complex } => complex.clone(),
+        Variant::Numeric { number } => Complex { re: number.to_si(globals).to_double(), im: 0.0, ..template.clone() },
+        _ => unreachable!()
+    };
+    let (c1, c2) = (to_complex(&args[0]), to_complex(&args[1]));
+    for arg in args {
+        if let Variant::Numeric { number } = &arg.variant {
+            if !number.unit.is_empty() {
+                errors.push(errors::w_complex_unit(&number.unit.id, arg.stmt_range.clone()));
+            }
+        }
+    }
+    let complex = match OperatorType::from_u8(OP) {
+        OperatorType::Plus => &c1 + &c2,
+        OperatorType::Min => &c1 - &c2,
+        OperatorType::Mult => &c1 * &c2,
+        OperatorType::Div => &c1 / &c2,
+        OperatorType::Power => c1.pow(&c2),
+        OperatorType::Equal => return Value::from_bool(c1.re == c2.re && c1.im == c2.im, range.clone()),
+        OperatorType::NotEqual => return Value::from_bool(c1.re != c2.re || c1.im != c2.im, range.clone()),
+        _ => unreachable!()
+    };
+    Value::from_complex(complex, range.clone())
+}
+
+///Element-wise: two lists of the same size, or a single value that is applied to every element of a list.
+pub fn op_list<const OP: u8>(globals: &Globals, args: &Vec<Value>, range: &Range, errors: &mut Vec<Error>) -> Value {
+    let op = OperatorType::from_u8(OP);
+    let values: Vec<Value> = match (&args[0].variant, &args[1].variant) {
+        (Variant::List { values: values1 }, Variant::List { values: values2 }) => {
+            if values1.len() != values2.len() {
+                let operands_range = operands_range(&args[0], &args[1], range);
+                errors.push(errors::list_size_diff(&values1.len().to_string(), &values2.len().to_string(), operands_range));
+                return Value::error(range.clone());
+            }
+            values1.iter().zip(values2.iter()).map(|(v1, v2)| op_element(globals, op, v1, v2, range, errors)).collect()
+        },
+        (Variant::List { values }, _) => values.iter().map(|value| op_element(globals, op, value, &args[1], range, errors)).collect(),
+        (_, Variant::List { values }) => values.iter().map(|value| op_element(globals, op, &args[0], value, range, errors)).collect(),
+        _ => unreachable!()
+    };
+    if values.iter().any(|value| value.has_errors) {
+        return Value::error(range.clone());
+    }
+    Value::from_list(values, range.clone())
+}
+
+fn op_element(globals: &Globals, op: OperatorType, value1: &Value, value2: &Value, range: &Range, errors: &mut Vec<Error>) -> Value {
+    let Some(operator) = globals.get_operator(operator_id_from(value1.variant.to_operand_type(), op, value2.variant.to_operand_type())) else {
+        errors.push(errors::no_op(&op.to_string(), value1.variant.name(), value2.variant.name(), operands_range(value1, value2, range)));
+        return Value::error(range.clone());
+    };
+    operator(globals, &vec![value1.clone(), value2.clone()], range, errors)
+}
+
+pub fn op_matrix_term_matrix<const OP: u8>(_globals: &Globals, args: &Vec<Value>, range: &Range, errors: &mut Vec<Error>) -> Value {
+    let Variant::Matrix { matrix: ref m1, unit: ref unit1 } = args[0].variant else { unreachable!(); };
+    let Variant::Matrix { matrix: ref m2, unit: ref unit2 } = args[1].variant else { unreachable!(); };
+    let adding = OP == OperatorType::Plus as u8;
+    let Some(matrix) = m1.zip(m2, |v1, v2| if adding { v1 + v2 } else { v1 - v2 }) else {
+        errors.push(errors::matrix_size_diff(&m1.size_text(), &m2.size_text(), operands_range(&args[0], &args[1], range)));
+        return Value::error(range.clone());
+    };
+    //both are in SI units, so the units only differ if the property differs.
+    if !unit1.is_empty() && !unit2.is_empty() && unit1.id != unit2.id {
+        errors.push(errors::unit_prop_diff(operands_range(&args[0], &args[1], range)));
+        return Value::error(range.clone());
+    }
+    let unit = if unit1.is_empty() { unit2.clone() } else { unit1.clone() };
+    Value::from_matrix(matrix, unit, range.clone())
+}
+
+pub fn op_matrix_mult_matrix(_globals: &Globals, args: &Vec<Value>, range: &Range, errors: &mut Vec<Error>) -> Value {
+    let Variant::Matrix { matrix: ref m1, unit: ref unit1 } = args[0].variant else { unreachable!(); };
+    let Variant::Matrix { matrix: ref m2, unit: ref unit2 } = args[1].variant else { unreachable!(); };
+    let Some(matrix) = m1.mul(m2) else {
+        errors.push(errors::matrix_size_diff(&m1.size_text(), &m2.size_text(), operands_range(&args[0], &args[1], range)));
+        return Value::error(range.clone());
+    };
+    Value::from_matrix(matrix, product_unit(unit1.clone(), unit2.clone()), range.clone())
+}
+
+///The list is a column vector. The result is a list.
+pub fn op_matrix_mult_list(globals: &Globals, args: &Vec<Value>, range: &Range, errors: &mut Vec<Error>) -> Value {
+    let Variant::Matrix { matrix: ref m, unit: ref matrix_unit } = args[0].variant else { unreachable!(); };
+    let Some(vector) = to_si_doubles(&args[1], globals) else {
+        errors.push(errors::inv_list("Expected a list of numbers.", args[1].stmt_range.clone()));
+        return Value::error(range.clone());
+    };
+    let vector_size = vector.len();
+    let Some(product) = m.mul(&Matrix::new(vector_size, 1, vector)) else {
+        errors.push(errors::matrix_size_diff(&m.size_text(), &format!("{}x1", vector_size), operands_range(&args[0], &args[1], range)));
+        return Value::error(range.clone());
+    };
+    let unit = product_unit(matrix_unit.clone(), si_unit_of_first(&args[1], globals));
+    let values = (0..product.rows).map(|row| Value::from_number(Number { significand: product.get(row, 0), exponent: 0, unit: unit.clone(), fmt: NumberFormat::Dec }, range.clone())).collect();
+    Value::from_list(values, range.clone())
+}
+
+pub fn op_matrix_scale<const OP: u8>(globals: &Globals, args: &Vec<Value>, range: &Range, _errors: &mut Vec<Error>) -> Value {
+    let (matrix, matrix_unit, number) = match (&args[0].variant, &args[1].variant) {
+        (Variant::Matrix { matrix, unit }, Variant::Numeric { number }) => (matrix, unit, number),
+        (Variant::Numeric { number }, Variant::Matrix { matrix, unit }) => (matrix, unit, number),
+        _ => unreachable!()
+    };
+    let number = number.to_si(globals);
+    let factor = number.to_double();
+    let (matrix, unit) = if OP == OperatorType::Div as u8 {
+        //the units of the same property divide out.
+        (matrix.map(|v| v / factor), if number.unit.is_empty() { matrix_unit.clone() } else { Unit::none() })
+    } else {
+        (matrix.map(|v| v * factor), product_unit(matrix_unit.clone(), number.unit.clone()))
+    };
+    Value::from_matrix(matrix, unit, range.clone())
+}
+
+///Same as multiplying numbers: there are no combined units.
+pub fn product_unit(unit1: Unit, unit2: Unit) -> Unit {
+    match (unit1.is_empty(), unit2.is_empty()) {
+        (_, true) => unit1,
+        (true, false) => unit2,
+        (false, false) => Unit::none(),
+    }
+}
+
+///The SI unit of the first number in the list.
+fn si_unit_of_first(list: &Value, globals: &Globals) -> Unit {
+    let Variant::List { values } = &list.variant else {
+        return Unit::none();
+    };
+    values.first()
+        .and_then(|value| value.as_number())
+        .map_or(Unit::none(), |number| number.to_si(globals).unit)
+}
+
+///None if not all values are numbers.
+pub fn to_si_doubles(list: &Value, globals: &Globals) -> Option<Vec<f64>> {
+    let Variant::List { values } = &list.variant else {
+        return None;
+    };
+    values.iter()
+        .map(|value| value.as_number().map(|number| number.to_si(globals).to_double()))
+        .collect()
+}
+
+///The range that covers both operands, if they are from the same source.
+pub fn operands_range(value1: &Value, value2: &Value, range: &Range) -> Range {
+    if value1.stmt_range.source_index == value2.stmt_range.source_index {
+        &value1.stmt_range + &value2.stmt_range
+    } else {
+        range.clone()
+    }
+}
+
+/// Generic over the operator, as the operator table only holds plain fn pointers.
+pub fn op_compare<const OP: u8>(globals: &Globals, args: &Vec<Value>, range: &Range, errors: &mut Vec<Error>) -> Value {
+    let ordering = match (&args[0].variant, &args[1].variant) {
+        (Variant::Numeric { number: n1 }, Variant::Numeric { number: n2 }) => compare_numbers(n1, n2, range, globals, errors),
+        (Variant::Date { date: d1 }, Variant::Date { date: d2 }) => Some(d1.compare(d2)),
+        (Variant::Duration { duration: d1 }, Variant::Duration { duration: d2 }) => Some(d1.to_days().cmp(&d2.to_days())),
+        (Variant::Bool { value: b1 }, Variant::Bool { value: b2 }) => Some(b1.cmp(b2)),
+        (Variant::Text { text: t1 }, Variant::Text { text: t2 }) => Some(t1.cmp(t2)),
+        _ => unreachable!() //has been checked.
+    };
+    let Some(ordering) = ordering else {
+        return Value::from_bool(OP == OperatorType::NotEqual as u8, range.clone()); //NaN
+    };
+    Value::from_bool(OperatorType::from_u8(OP).compare(ordering), range.clone())
+}
+
+fn compare_numbers(n1: &Number, n2: &Number, range: &Range, globals: &Globals, errors: &mut Vec<Error>) -> Option<Ordering> {
+    let (d1, d2) = to_comparable_doubles(n1, n2, range, globals, errors)?;
+    d1.partial_cmp(&d2)
+}
+
+///`a ~= b within tolerance`: the tolerance is the third argument. A tolerance without unit is in SI units.
+pub fn op_num_approx_num(globals: &Globals, args: &Vec<Value>, range: &Range, errors: &mut Vec<Error>) -> Value {
+    let Variant::Numeric {number: ref n1, ..} = &args[0].variant else { unreachable!(); };
+    let Variant::Numeric {number: ref n2, ..} = &args[1].variant else { unreachable!(); };
+    let Variant::Numeric {number: ref tolerance, ..} = &args[2].variant else { unreachable!(); };
+    let Some((d1, d2)) = to_comparable_doubles(n1, n2, range, globals, errors) else {
+        return Value::from_bool(false, range.clone());
+    };
+    if tolerance.to_double() < 0.0 {
+        errors.push(errors::value_error("The tolerance can't be negative.", args[2].stmt_range.clone()));
+        return Value::error(range.clone());
+    }
+    let operand_unit = if n1.unit.is_empty() { &n2.unit } else { &n1.unit };
+    let tolerance = match globals.unit_defs.get(&tolerance.unit.id) {
+        Some(unit_def) => {
+            if globals.unit_defs.get(&operand_unit.id).is_some_and(|operand_unit_def| operand_unit_def.property != unit_def.property) {
+                errors.push(errors::unit_prop_diff(args[2].stmt_range.clone()));
+            }
+            //a difference, so no offset as for temperatures.
+            unit_def.convert_to_si(tolerance.to_double()) - unit_def.convert_to_si(0.0)
+        },
+        None => tolerance.to_double(),
+    };
+    Value::from_bool((d1 - d2).abs() <= tolerance, range.clone())
+}
+
+///Same as do_term(): compare in SI units if both values have units.
+fn to_comparable_doubles(n1: &Number, n2: &Number, range: &Range, globals: &Globals, errors: &mut Vec<Error>) -> Option<(f64, f64)> {
+    if !n1.unit.is_empty() && !n2.unit.is_empty() {
+        let Some(u1) = &globals.unit_defs.get(&n1.unit.id) else {
+            errors.push(errors::unit_not_def(&n1.unit.id, n1.unit.range.as_ref().unwrap_or(range).clone()));
+            return None;
+        };
+        let Some(u2) = &globals.unit_defs.get(&n2.unit.id) else {
+            errors.push(errors::unit_not_def(&n2.unit.id, n2.unit.range.as_ref().unwrap_or(range).clone()));
+            return None;
+        };
+        if u1.property != u2.property {
+            errors.push(errors::unit_prop_diff(range.clone()));
+        }
+        Some((n1.to_si(globals).to_double(), n2.to_si(globals).to_double()))
+    } else {
+        if !n1.unit.is_empty() || ! n2.unit.is_empty() {
+            errors.push(errors::w_assuming_unit(n2.unit.range.as_ref().unwrap_or(range).clone()));
+        }
+        Some((n1.to_double(), n2.to_double()))
+    }
 }
 
 fn do_term(v1: &Number, adding: bool, v2: &Number, range: &Range, globals: &Globals, errors: &mut Vec<Error>) -> Number {
@@ -1,12 +1,17 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 use crate::date::DateFormat;
 use crate::functions::{CustomFunctionDef, execute_custom_function, FunctionDef, FunctionView};
-use crate::parser::nodes::{CodeBlock, FunctionDefExpr};
+use crate::parser::nodes::{CodeBlock, FunctionDefExpr, Node, NodeType};
 use crate::globals::Globals;
+use crate::number::Number;
 use crate::resolver::unit::{UnitsView};
-use crate::resolver::value::Value;
+use crate::resolver::value::{Value, Variant};
+use crate::tokenizer::cursor::Range;
+
+///Deeper recursion would overflow the stack, which is only 1MB in wasm.
+pub const MAX_RECURSION: usize = 100;
 
 #[derive(Clone, Copy)]
 pub enum DecimalChar { Dot, Comma, Auto }
@@ -14,7 +19,10 @@ pub enum DecimalChar { Dot, Comma, Auto }
 pub struct Scope {
     pub parent_scope: Option<Rc<RefCell<Scope>>>,
     pub var_defs: HashSet<String>,
+    pub function_ref_defs: HashSet<String>, //variables that are assigned a function or lambda, as parsed.
     pub variables: HashMap<String, Value>,
+    pub variable_order: Vec<String>, //definition order of the variables, for selectors.
+    pub const_defs: HashSet<String>, //variables that are declared with `const`.
     pub function_view: FunctionView,
     pub local_function_defs:  HashMap<String, CustomFunctionDef>,
     pub units_view: UnitsView,
@@ -22,6 +30,10 @@ pub struct Scope {
     pub precision: f64,
     pub strict: bool,
     pub decimal_char: DecimalChar,
+    pub max_recursion: usize,
+    pub physics: bool, //physical constants are available.
+    pub electric: bool, //the imaginary unit is `j` instead of `i`.
+    pub inherit_variables: bool, //variables of the parent scope are visible, as in the branches of an if statement.
 }
 
 impl Scope {
@@ -29,7 +41,10 @@ impl Scope {
         Scope {
             parent_scope: None,
             var_defs: HashSet::new(),
+            function_ref_defs: HashSet::new(),
             variables: HashMap::new(),
+            variable_order: Vec::new(),
+            const_defs: HashSet::new(),
             function_view: globals.function_view.clone(),
             local_function_defs: HashMap::new(),
             units_view: globals.units_view.clone(),
@@ -37,6 +52,10 @@ impl Scope {
             precision: 10.0_f64.powf(5.0),
             strict: false,
             decimal_char: DecimalChar::Auto,
+            max_recursion: MAX_RECURSION,
+            physics: false,
+            electric: false,
+            inherit_variables: false,
         }
     }
 
@@ -51,50 +70,213 @@ impl Scope {
             precision: scope.precision,
             strict: scope.strict,
             decimal_char: scope.decimal_char,
+            max_recursion: scope.max_recursion,
+            physics: scope.physics,
+            electric: scope.electric,
+            inherit_variables: false,
 
             //don't copy:
             local_function_defs: HashMap::new(),
             var_defs: HashSet::new(),
+            function_ref_defs: HashSet::new(),
             variables: HashMap::new(),
+            variable_order: Vec::new(),
+            const_defs: HashSet::new(),
         })
     }
 
-    pub fn add_local_function(&mut self, code_block: CodeBlock, function_def_expr: &FunctionDefExpr) {
+    pub fn add_local_function(&mut self, code_block: CodeBlock, function_def_expr: &FunctionDefExpr, default_values: Vec<Box<Node>>) {
+        let fixed_args = function_def_expr.arg_names.len() - function_def_expr.variadic as usize;
         let func = CustomFunctionDef {
             code_block,
             function_def_expr: function_def_expr.clone(),
-            name: function_def_expr.id.clone(),
-            min_args: function_def_expr.arg_names.len(),
-            max_args: function_def_expr.arg_names.len(),
-            execute: execute_custom_function
+            name: function_def_expr.name.clone(),
+            min_args: fixed_args - default_values.len(),
+            max_args: if function_def_expr.variadic { usize::MAX } else { fixed_args },
+            execute: execute_custom_function,
+            default_values,
+            depth: Cell::new(0),
         };
-        self.local_function_defs.insert(func.name.clone(), func);
+        self.local_function_defs.insert(function_def_expr.id.clone(), func);
+    }
+
+    ///A function is added before its body is parsed, so that it can call itself.
+    pub fn set_local_function_body(&mut self, id: &str, code_block: CodeBlock, range: Range) {
+        if let Some(func) = self.local_function_defs.get_mut(id) {
+            func.code_block = code_block;
+            func.function_def_expr.range = range;
+        }
+    }
+
+    pub fn get_variable(&self, id: &str) -> Option<Value> {
+        if let Some(value) = self.variables.get(id) {
+            return Some(value.clone());
+        }
+        if !self.inherit_variables {
+            return None;
+        }
+        self.parent_scope.as_ref().and_then(|parent_scope| parent_scope.borrow().get_variable(id))
+    }
+
+    ///Also for the scopes that don't inherit variables, like a function body: a local variable can't hide a constant.
+    pub fn is_const_def(&self, id: &str) -> bool {
+        if self.const_defs.contains(id) {
+            return true;
+        }
+        self.parent_scope.as_ref().is_some_and(|parent_scope| parent_scope.borrow().is_const_def(id))
+    }
+
+    ///A built-in constant, or a physical constant if these are defined.
+    pub fn get_constant(&self, id: &str, globals: &Globals) -> Option<Number> {
+        globals.constants.get(id)
+            .or_else(|| if self.physics { globals.physics_constants.get(id) } else { None })
+            .cloned()
+    }
+
+    pub fn imaginary_unit(&self) -> char {
+        if self.electric { 'j' } else { 'i' }
+    }
+
+    pub fn set_variable(&mut self, id: &str, value: Value) {
+        if !self.variables.contains_key(id) && !self.variable_order.iter().any(|var| var == id) {
+            self.variable_order.push(id.to_string());
+        }
+        self.variables.insert(id.to_string(), value);
+    }
+
+    ///The variables of the function call that this scope is part of. A lambda captures these, as the next call replaces them.
+    ///The global variables are not captured.
+    pub fn frame_variables(&self) -> HashMap<String, Value> {
+        let Some(parent_scope) = &self.parent_scope else {
+            return HashMap::new();
+        };
+        let mut variables = if self.inherit_variables { parent_scope.borrow().frame_variables() } else { HashMap::new() };
+        variables.extend(self.variables.iter().map(|(id, value)| (id.clone(), value.clone())));
+        variables
+    }
+
+    ///Returns the values of the variables that match, in the order the variables were defined.
+    pub fn select_variables(&self, is_match: impl Fn(&str) -> bool) -> Vec<Value> {
+        let mut ids: Vec<&String> = self.variables.keys().filter(|id| is_match(id)).collect();
+        ids.sort_by_key(|id| (self.variable_order.iter().position(|var| var == *id).unwrap_or(usize::MAX), id.to_string()));
+        ids.iter().map(|id| self.variables[*id].clone()).collect()
     }
 
     #[inline]
     pub fn function_accessible(&self, id: &str) -> bool {
         self.function_view.ids.contains(id)
-        //don't check parent: functions can be hidden within a block.
+            || self.parent_scope.as_ref().is_some_and(|parent_scope| parent_scope.borrow().custom_function_defined(id))
+        //don't check the global functions of the parent: they can be hidden within a block.
     }
 
-    pub fn with_function<TReturnValue> (&self, id: &str, globals: &Globals, mut f: impl FnMut(&dyn FunctionDef) -> TReturnValue) -> Option<TReturnValue> {
-        if let Some(fdef) = self.local_function_defs.get(id) {
-           Some(f(fdef))
+    ///A custom function that is defined here or in a parent scope, before the code that looks for it is run.
+    fn custom_function_defined(&self, id: &str) -> bool {
+        let is_defined = self.function_view.ids.contains(id)
+            && (self.local_function_defs.contains_key(id) || self.local_function_defs.values().any(|fd| fd.name == id));
+        is_defined || self.parent_scope.as_ref().is_some_and(|parent_scope| parent_scope.borrow().custom_function_defined(id))
+    }
+
+    ///Only the scope that holds the function stays borrowed during the call, so that a (recursive) call can still modify the other scopes.
+    pub fn with_function<TReturnValue> (scope: &Rc<RefCell<Scope>>, id: &str, globals: &Globals, mut f: impl FnMut(&dyn FunctionDef) -> TReturnValue) -> Option<TReturnValue> {
+        if scope.borrow().local_function_defs.contains_key(id) {
+           return Some(f(&scope.borrow().local_function_defs[id]));
+        }
+        let parent_scope = scope.borrow().parent_scope.clone();
+        if let Some(parent_scope) = parent_scope {
+            Scope::with_function(&parent_scope, id, globals, f)
         } else {
-            if let Some(parent_scope) = self.parent_scope.as_ref() {
-                parent_scope.borrow().with_function(id, globals, f)
+            if let Some(fdef) = globals.global_function_defs.get(id) {
+                Some(f(fdef))
             } else {
-                if let Some(fdef) = globals.global_function_defs.get(id) {
-                    Some(f(fdef))
-                } else {
-                    None
-                }
+                None
             }
         }
     }
 
+    pub fn function_body_scope(scope: &Rc<RefCell<Scope>>, id: &str) -> Option<Rc<RefCell<Scope>>> {
+        if let Some(function_def) = scope.borrow().local_function_defs.get(id) {
+            return Some(function_def.code_block.scope.clone());
+        }
+        let parent_scope = scope.borrow().parent_scope.clone();
+        parent_scope.and_then(|parent_scope| Scope::function_body_scope(&parent_scope, id))
+    }
+
+    ///Runs f with the captured variables of a lambda in a scope between its body and the scope that defines it.
+    pub fn with_captures<TReturnValue>(body_scope: &Rc<RefCell<Scope>>, captures: &HashMap<String, Value>, f: impl FnOnce() -> TReturnValue) -> TReturnValue {
+        let Some(definition_scope) = body_scope.borrow().parent_scope.clone() else {
+            return f();
+        };
+        let closure = Scope::copy_for_block(&definition_scope);
+        closure.borrow_mut().inherit_variables = true;
+        closure.borrow_mut().variables = captures.clone();
+        body_scope.borrow_mut().parent_scope = Some(Rc::new(closure));
+        let result = f();
+        body_scope.borrow_mut().parent_scope = Some(definition_scope);
+        result
+    }
+
+    pub fn custom_function_exists(&self, function_name: &str) -> bool {
+        if self.local_function_defs.values().any(|fd| fd.name == function_name) {
+            return true;
+        }
+        self.parent_scope.as_ref().is_some_and(|parent_scope| parent_scope.borrow().custom_function_exists(function_name))
+    }
+
+    ///A custom function of which the last statement is a lambda, as in `function make_adder(a) { x => x+a; }`
+    pub fn returns_lambda(&self, function_name: &str) -> bool {
+        let returns_lambda = self.local_function_defs.values()
+            .filter(|fd| fd.name == function_name)
+            .any(|fd| fd.code_block.statements.last().is_some_and(|statement| matches!(statement.node.expr, NodeType::Lambda(_))));
+        returns_lambda || self.parent_scope.as_ref().is_some_and(|parent_scope| parent_scope.borrow().returns_lambda(function_name))
+    }
+
     #[inline]
     pub fn function_exists(&self, function_name: &str, globals: &Globals) -> bool {
-        self.with_function(function_name, globals, |_fd| ()).is_some()
+        self.custom_function_exists(function_name) || globals.global_function_defs.contains_key(function_name)
+    }
+
+    ///Returns the decorated id of the custom function overload that accepts the args.
+    ///Global functions aren't overloaded and keep their name.
+    ///If none of the overloads fits, the error contains a description of the available number of args.
+    pub fn overload_id(&self, function_name: &str, args: &Vec<Value>) -> Result<String, String> {
+        let mut mismatches = Vec::new();
+        if let Some(id) = self.find_overload(function_name, args, &mut mismatches) {
+            return Ok(id);
+        }
+        match mismatches.len() {
+            0 => return Ok(function_name.to_string()),
+            1 => return Ok(mismatches.remove(0).0), //not overloaded: let the call report the wrong arg count.
+            _ => ()
+        }
+        let mut arities: Vec<(usize, usize)> = mismatches.into_iter().map(|(_, min, max)| (min, max)).collect();
+        arities.sort();
+        Err(arities.iter()
+            .map(|(min, max)| match (min, max) {
+                (min, max) if min == max => min.to_string(),
+                (min, &usize::MAX) => format!("{} or more", min),
+                (min, max) => format!("{} to {}", min, max),
+            })
+            .collect::<Vec<String>>()
+            .join(", "))
+    }
+
+    fn find_overload(&self, function_name: &str, args: &Vec<Value>, mismatches: &mut Vec<(String, usize, usize)>) -> Option<String> {
+        let overloads: Vec<(&String, &CustomFunctionDef)> = self.local_function_defs.iter()
+            .filter(|(_, fd)| fd.name == function_name)
+            .collect();
+        let mut arg_count = args.len();
+        if let [Value { variant: Variant::List { values }, .. }] = args.as_slice() {
+            if !overloads.iter().any(|(_, fd)| fd.is_correct_arg_count(arg_count)) {
+                arg_count = values.len(); //the list will be exploded.
+            }
+        }
+        let best_fit = overloads.iter()
+            .filter(|(_, fd)| fd.is_correct_arg_count(arg_count))
+            .min_by_key(|(_, fd)| fd.function_def_expr.arg_names.len());
+        if let Some((id, _)) = best_fit {
+            return Some(id.to_string());
+        }
+        mismatches.extend(overloads.iter().map(|(id, fd)| (id.to_string(), fd.min_args, fd.max_args)));
+        self.parent_scope.as_ref().and_then(|parent_scope| parent_scope.borrow().find_overload(function_name, args, mismatches))
     }
 }
\ No newline at end of file
@@ -3,6 +3,7 @@ use std::rc::Rc;
 use serde::{Serialize, Serializer};
 use serde::ser::SerializeStruct;
 use crate::{date, errors};
+use crate::complex::ComplexFormat;
 use crate::globals::Globals;
 use crate::number::Number;
 use crate::number_format::NumberFormat;
@@ -63,6 +64,9 @@ impl<'a> Serialize for ScopedValue<'a> {
         state.serialize_field("src", &self.value.stmt_range.source_index.as_int())?;
         let (line, _) = self.globals.get_line_and_column(&self.value.stmt_range);
         state.serialize_field("line", &line)?;
+        if let Some(echo) = &self.value.echo {
+            state.serialize_field("echo", self.globals.get_text(echo))?;
+        }
 
         match &self.value.variant {
             Numeric { number, .. } => {
@@ -70,15 +74,30 @@ impl<'a> Serialize for ScopedValue<'a> {
             },
             Date { date } => state.serialize_field("date", date),
             Duration { duration } => state.serialize_field("duration", duration),
-            Comment  => state.serialize_field("comment", self.globals.get_text(&self.value.stmt_range)),
+            Bool { value } => state.serialize_field("bool", value),
+            Text { text } => state.serialize_field("text", text),
+            Comment { text } => state.serialize_field("comment", text),
             FunctionDef => {
                 let function_name =  self.globals.get_text(&self.value.stmt_range).to_string();
                 state.serialize_field("function", &function_name)
             },
+            FunctionRef { name, .. } => state.serialize_field("function", name),
             List { values }=> {
                 let scoped_values: Vec<ScopedValue> = values.iter().map(|v| ScopedValue { scope: self.scope.clone(), globals: &self.globals, value: &v }).collect();
                 state.serialize_field("list", &scoped_values)
             },
+            Record { fields } => {
+                let scoped_fields = RecordContext { fields, scope: self.scope.clone(), globals: self.globals };
+                state.serialize_field("record", &scoped_fields)
+            },
+            Matrix { matrix, unit } => {
+                let rows: Vec<Vec<String>> = (0..matrix.rows)
+                    .map(|row| matrix.row(row).iter().map(|v| format!("{}{}", format_number(&Number::from(*v), self.scope.borrow().precision), unit.id)).collect())
+                    .collect();
+                state.serialize_field("matrix", &rows)
+            },
+            Complex { complex } => state.serialize_field("complex", &format_complex(complex, self.scope.borrow().precision)),
+            Expression { expression } => state.serialize_field("expression", &expression.to_string()),
             Last => {
                 state.serialize_field("Last", "last")
             },
@@ -91,6 +110,20 @@ impl<'a> Serialize for ScopedValue<'a> {
     }
 }
 
+///A record is serialized as a map, in the order of its fields.
+struct RecordContext<'a> {
+    fields: &'a Vec<(String, Value)>,
+    scope: Rc<RefCell<Scope>>,
+    globals: &'a Globals,
+}
+
+impl<'a> Serialize for RecordContext<'a> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        serializer.collect_map(self.fields.iter()
+            .map(|(name, value)| (name, ScopedValue { scope: self.scope.clone(), globals: self.globals, value })))
+    }
+}
+
 impl Serialize for Unit {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
         serializer.serialize_str(&self.id)
@@ -102,6 +135,38 @@ fn reduce_precision(n: f64, prec: f64) -> f64 {
     (n*prec).round()/prec
 }
 
+///Formats the number in its own notation (`fmt`), without unit.
+pub fn format_number(number: &Number, precision: f64) -> String {
+    let reduced_precision = reduce_precision(number.to_double(), precision);
+    match &number.fmt {
+        NumberFormat::Dec => format!("{}", reduced_precision),
+        NumberFormat::Hex => format!("0x{:0X}", reduced_precision as u64),
+        NumberFormat::Oct => format!("0o{:0o}", reduced_precision as u64),
+        NumberFormat::Bin => format!("0b{:0b}", reduced_precision as u64),
+        NumberFormat::Exp => {
+            let norm = number.normalize_number();
+            format!("{0}e{1}", reduce_precision(norm.significand, precision), norm.exponent)
+        },
+    }
+}
+
+///`3 + 4i`, or in polar form `5∠53.1301°`, with the angle in degrees.
+pub fn format_complex(complex: &crate::complex::Complex, precision: f64) -> String {
+    let format = |value: f64| format_number(&Number::from(value), precision);
+    match complex.fmt {
+        ComplexFormat::Polar => format!("{}∠{}°", format(complex.abs()), format(complex.arg_in_degrees())),
+        ComplexFormat::Rect => {
+            let sign = if complex.im < 0.0 { "-" } else { "+" };
+            let im = format(complex.im.abs());
+            if reduce_precision(complex.re, precision) == 0.0 {
+                format!("{}{}{}", if complex.im < 0.0 { "-" } else { "" }, im, complex.symbol)
+            } else {
+                format!("{} {} {}{}", format(complex.re), sign, im, complex.symbol)
+            }
+        },
+    }
+}
+
 struct NumberContext<'n> {
     number: &'n Number,
     scope: Rc<RefCell<Scope>>,
@@ -115,18 +180,7 @@ impl<'n> Serialize for NumberContext<'n> {
         state.serialize_field("exp", &self.number.exponent)?;
         state.serialize_field("u", &self.number.unit)?;
         state.serialize_field("fmt", &self.number.fmt)?;
-        let precision = self.scope.borrow().precision;
-        let reduced_precision = reduce_precision(self.number.to_double(), precision);
-        let fmtd = match &self.number.fmt {
-            NumberFormat::Dec => format!("{}", reduced_precision),
-            NumberFormat::Hex => format!("0x{:0X}", reduced_precision as u64),
-            NumberFormat::Oct => format!("0o{:0o}", reduced_precision as u64),
-            NumberFormat::Bin => format!("0b{:0b}", reduced_precision as u64),
-            NumberFormat::Exp => {
-                let norm = self.number.normalize_number();
-                format!("{0}e{1}", reduce_precision(norm.significand, precision), norm.exponent)
-            },
-        };
+        let fmtd = format_number(self.number, self.scope.borrow().precision);
         state.serialize_field("fmtd", &fmtd)?;
         state.end()
     }
@@ -242,12 +296,6 @@ impl Serialize for date::Timepoint {
             S: Serializer
     {
         let mut state = serializer.serialize_struct("Timepoint", 4)?;
-        let norm_day = self.get_normalized_day();
-        let str_day_formatted = if norm_day == 0 {
-            "??".to_string()
-        } else {
-            norm_day.to_string()
-        };
         let str_day = if self.day.is_none() {
             "--".to_string()
         } else {
@@ -258,8 +306,7 @@ impl Serialize for date::Timepoint {
             }
         };
         let str_year = if let Some(year) = self.year { year.to_string()} else { "????".to_string()};
-        let formatted = format!("{0}-{1:?}-{2}", &str_year, &self.month, &str_day_formatted);
-        state.serialize_field("formatted", &formatted)?;
+        state.serialize_field("formatted", &self.to_formatted_string())?;
         state.serialize_field("day", &str_day)?;
         state.serialize_field("month", &self.month)?;
         state.serialize_field("year", &str_year)?;
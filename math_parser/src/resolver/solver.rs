@@ -0,0 +1,280 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use crate::errors;
+use crate::errors::{has_real_errors, Error, ErrorId, ErrorType};
+use crate::functions::to_text;
+use crate::number::Number;
+use crate::parser::nodes::{CallExpr, HasRange, Node, NodeType};
+use crate::resolver::operator::OperatorType;
+use crate::resolver::Resolver;
+use crate::resolver::scope::Scope;
+use crate::resolver::unit::Unit;
+use crate::resolver::value::{Value, Variant};
+use crate::tokenizer::cursor::Range;
+use crate::tokenizer::token_type::TokenType;
+
+const MAX_NEWTON_STEPS: usize = 50;
+const MAX_BRACKET_STEPS: usize = 40; //the search interval doubles with every step.
+const MAX_BISECTIONS: usize = 1100; //enough to reach the precision of a f64.
+
+enum Guess {
+    Start(f64),
+    Interval(f64, f64), //lower, upper.
+}
+
+impl Guess {
+    fn start(&self) -> f64 {
+        match self {
+            Guess::Start(start) => *start,
+            Guess::Interval(lower, upper) => lower + (upper - lower) / 2.0,
+        }
+    }
+}
+
+enum RootError {
+    NoRoot(f64, f64), //the interval that was searched.
+    NoConvergence,
+    Evaluation, //the errors are already reported.
+}
+
+///The unknown of an equation, as a variable in its own scope, so that the equation can be resolved for any value of it.
+struct Unknown {
+    name: String,
+    unit: Unit,
+    scope: Rc<RefCell<Scope>>,
+    range: Range,
+}
+
+impl<'g, 'a> Resolver<'g, 'a> {
+    ///`solve(equation, x, guess)`, where the equation is an expression that should be zero, or `lhs == rhs`.
+    ///The guess is a number to start from, or a list of two numbers that enclose the root.
+    ///Returns None if the call has another form, like `solve(matrix, list)`.
+    pub(super) fn resolve_equation_solve(&mut self, call_expr: &CallExpr) -> Option<Value> {
+        if call_expr.function_name != "solve" || call_expr.arguments.len() != 3 || self.scope.borrow().custom_function_exists("solve") {
+            return None;
+        }
+        let NodeType::Id(id_expr) = &call_expr.arguments[1].expr else {
+            return None;
+        };
+        let equation = &call_expr.arguments[0];
+        let guess = self.resolve_node(&call_expr.arguments[2]);
+        if guess.has_errors {
+            return Some(Value::error(call_expr.get_range()));
+        }
+        let Some((guess, unit)) = self.read_guess(&guess) else {
+            return Some(self.add_error_value(errors::func_arg_wrong_type("solve", "Expected a number or a list of 2 numbers.", call_expr.arguments[2].get_range())));
+        };
+        let scope = Rc::new(Scope::copy_for_block(&self.scope));
+        scope.borrow_mut().inherit_variables = true;
+        let mut unknown = Unknown {
+            name: self.globals.get_text(&id_expr.id.range).to_string(),
+            unit,
+            scope,
+            range: id_expr.get_range(),
+        };
+        if unknown.unit.is_empty() {
+            unknown.unit = self.implied_unit(equation, &unknown, guess.start());
+        }
+
+        let root = find_root(&mut |x| self.residual_at(equation, &unknown, x), guess);
+        let root = match root {
+            Ok(root) => root,
+            Err(RootError::Evaluation) => return Some(Value::error(call_expr.get_range())),
+            Err(RootError::NoConvergence) => return Some(self.add_error_value(errors::solve_no_convergence(&unknown.name, call_expr.function_name_range.clone()))),
+            Err(RootError::NoRoot(lower, upper)) => {
+                let precision = self.scope.borrow().precision;
+                let lower = to_text(&unknown.to_value(lower), precision).unwrap_or_default();
+                let upper = to_text(&unknown.to_value(upper), precision).unwrap_or_default();
+                return Some(self.add_error_value(errors::solve_no_root(&unknown.name, &lower, &upper, call_expr.function_name_range.clone())));
+            },
+        };
+        let mut result = unknown.to_value(root);
+        result.stmt_range = call_expr.get_range();
+        Some(result)
+    }
+
+    fn read_guess(&mut self, guess: &Value) -> Option<(Guess, Unit)> {
+        match &guess.variant {
+            Variant::Numeric { number } => Some((Guess::Start(number.to_double()), number.unit.clone())),
+            Variant::List { values } if values.len() == 2 => {
+                let (Some(lower), Some(upper)) = (values[0].as_number(), values[1].as_number()) else {
+                    return None;
+                };
+                let mut upper = upper.clone();
+                if !lower.unit.is_empty() && !upper.unit.is_empty() {
+                    upper.convert_to_unit(&lower.unit, &self.scope.borrow().units_view, &guess.stmt_range, self.errors, self.globals);
+                }
+                let unit = if lower.unit.is_empty() { upper.unit.clone() } else { lower.unit.clone() };
+                let (lower, upper) = (lower.to_double(), upper.to_double());
+                Some((Guess::Interval(lower.min(upper), lower.max(upper)), unit))
+            },
+            _ => None,
+        }
+    }
+
+    ///If the unknown has no unit, but the equation needs one to be consistent, as in `x + 2m == 5m`,
+    ///the unknown gets the unit that is assumed for it.
+    fn implied_unit(&mut self, equation: &Box<Node>, unknown: &Unknown, start: f64) -> Unit {
+        let mut errors = Vec::new();
+        let residual = self.resolve_residual(equation, unknown, start, &mut errors);
+        if !errors.iter().any(|error| error.id == ErrorId::WAssumingUnit) {
+            return Unit::none();
+        }
+        //the warning points to the unit of the other value, if it has one.
+        let assumed_unit = errors.iter()
+            .filter(|error| error.id == ErrorId::WAssumingUnit)
+            .map(|error| self.globals.get_text(&error.range))
+            .find(|id| self.globals.unit_defs.contains_key(*id));
+        match assumed_unit {
+            Some(id) => Unit::from_id(id, None),
+            None => residual.as_number().map_or(Unit::none(), |number| number.unit.clone()),
+        }
+    }
+
+    ///Resolves the equation, with the unknown set to x.
+    fn resolve_residual(&mut self, equation: &Box<Node>, unknown: &Unknown, x: f64, errors: &mut Vec<Error>) -> Value {
+        unknown.scope.borrow_mut().set_variable(&unknown.name, unknown.to_value(x));
+        let mut resolver = Resolver {globals: self.globals, scope: unknown.scope.clone(), results: Vec::new(), errors, muted: true, current_statement_muted: false, last_result: self.last_result.clone(), echo_results: self.echo_results.clone(), line_results: Vec::new()};
+        match &equation.expr {
+            NodeType::Binary(bin_expr) if bin_expr.op.kind == TokenType::EqEq => {
+                let lhs = resolver.resolve_node(&bin_expr.expr1);
+                let rhs = resolver.resolve_node(&bin_expr.expr2);
+                if lhs.has_errors || rhs.has_errors {
+                    return Value::error(equation.get_range());
+                }
+                resolver.apply_operator(OperatorType::Min, vec![lhs, rhs], &equation.get_range())
+            },
+            _ => resolver.resolve_node(equation),
+        }
+    }
+
+    ///Only errors are reported: warnings would be repeated for every value that is tried.
+    fn residual_at(&mut self, equation: &Box<Node>, unknown: &Unknown, x: f64) -> Result<f64, RootError> {
+        let mut errors = Vec::new();
+        let residual = self.resolve_residual(equation, unknown, x, &mut errors);
+        if has_real_errors(&errors) {
+            self.errors.extend(errors.into_iter().filter(|error| error.error_type == ErrorType::E));
+            return Err(RootError::Evaluation);
+        }
+        //a complex result, as for sqrt(x) with x < 0, means the equation isn't defined there.
+        if let Variant::Complex { .. } = residual.variant {
+            return Ok(f64::NAN);
+        }
+        let Some(number) = residual.as_number() else {
+            self.errors.push(errors::func_arg_wrong_type("solve", "The equation should result in a number.", equation.get_range()));
+            return Err(RootError::Evaluation);
+        };
+        Ok(number.to_double())
+    }
+}
+
+impl Unknown {
+    fn to_value(&self, x: f64) -> Value {
+        let mut number = Number::from(x);
+        number.unit = self.unit.clone();
+        Value::from_number(number, self.range.clone())
+    }
+}
+
+///Newton's method first, as it's fast and precise. If that fails, look for an interval with a sign change and bisect it.
+fn find_root(f: &mut impl FnMut(f64) -> Result<f64, RootError>, guess: Guess) -> Result<f64, RootError> {
+    let (lower, upper) = match guess {
+        Guess::Interval(lower, upper) => (lower, upper),
+        Guess::Start(start) => {
+            if let Some(root) = newton(f, start)? {
+                return Ok(root);
+            }
+            bracket(f, start)?
+        },
+    };
+    bisect(f, lower, upper)
+}
+
+///With a numerical derivative. None if it doesn't converge.
+fn newton(f: &mut impl FnMut(f64) -> Result<f64, RootError>, start: f64) -> Result<Option<f64>, RootError> {
+    let mut x = start;
+    for _ in 0..MAX_NEWTON_STEPS {
+        let f_x = f(x)?;
+        if f_x == 0.0 {
+            return Ok(Some(x));
+        }
+        let h = 1e-7 * if x == 0.0 { 1.0 } else { x.abs() };
+        let slope = (f(x + h)? - f(x - h)?) / (2.0 * h);
+        let step = f_x / slope;
+        if !step.is_finite() {
+            return Ok(None);
+        }
+        x -= step;
+        if step.abs() <= 1e-12 * x.abs() {
+            return Ok(Some(x));
+        }
+    }
+    Ok(None)
+}
+
+///Widens an interval around the start value until the function changes sign at one of its sides.
+fn bracket(f: &mut impl FnMut(f64) -> Result<f64, RootError>, start: f64) -> Result<(f64, f64), RootError> {
+    let f_start = f(start)?;
+    let (mut lower, mut f_lower) = (start, f_start);
+    let (mut upper, mut f_upper) = (start, f_start);
+    let mut step = 0.1 * start.abs().max(1.0);
+    for _ in 0..MAX_BRACKET_STEPS {
+        let (next_lower, next_upper) = (start - step, start + step);
+        let (f_next_lower, f_next_upper) = (f(next_lower)?, f(next_upper)?);
+        if changes_sign(f_upper, f_next_upper) {
+            return Ok((upper, next_upper));
+        }
+        if changes_sign(f_lower, f_next_lower) {
+            return Ok((next_lower, lower));
+        }
+        //skip the values where the function isn't defined, like sqrt(x) for x < 0.
+        if f_next_lower.is_finite() {
+            (lower, f_lower) = (next_lower, f_next_lower);
+        }
+        if f_next_upper.is_finite() {
+            (upper, f_upper) = (next_upper, f_next_upper);
+        }
+        step *= 2.0;
+    }
+    Err(RootError::NoRoot(start - step / 2.0, start + step / 2.0))
+}
+
+///Bisects until the interval can't get any smaller. A sign change that isn't a root, like a pole of `1/x`, doesn't converge.
+fn bisect(f: &mut impl FnMut(f64) -> Result<f64, RootError>, lower: f64, upper: f64) -> Result<f64, RootError> {
+    let (mut lower, mut upper) = (lower, upper);
+    let (mut f_lower, mut f_upper) = (f(lower)?, f(upper)?);
+    if f_lower == 0.0 {
+        return Ok(lower);
+    }
+    if f_upper == 0.0 {
+        return Ok(upper);
+    }
+    if !changes_sign(f_lower, f_upper) {
+        return Err(RootError::NoRoot(lower, upper));
+    }
+    let scale = f_lower.abs().max(f_upper.abs());
+    for _ in 0..MAX_BISECTIONS {
+        let middle = lower + (upper - lower) / 2.0;
+        if middle == lower || middle == upper {
+            break;
+        }
+        let f_middle = f(middle)?;
+        if f_middle == 0.0 {
+            return Ok(middle);
+        }
+        if changes_sign(f_lower, f_middle) {
+            (upper, f_upper) = (middle, f_middle);
+        } else {
+            (lower, f_lower) = (middle, f_middle);
+        }
+    }
+    let (root, f_root) = if f_lower.abs() < f_upper.abs() { (lower, f_lower) } else { (upper, f_upper) };
+    if f_root.abs() > 1e-6 * scale {
+        return Err(RootError::NoConvergence);
+    }
+    Ok(root)
+}
+
+fn changes_sign(f1: f64, f2: f64) -> bool {
+    f1.is_finite() && f2.is_finite() && (f1 < 0.0) != (f2 < 0.0)
+}
@@ -0,0 +1,92 @@
+use crate::errors;
+use crate::errors::Error;
+use crate::expression::Expression;
+use crate::parser::nodes::{CallExpr, ConstType, HasRange, Node, NodeType};
+use crate::resolver::Resolver;
+use crate::resolver::value::{Value, Variant};
+use crate::tokenizer::token_type::TokenType;
+
+impl<'g, 'a> Resolver<'g, 'a> {
+    ///`derive(expr, x)` and `simplify(expr)`: the expression isn't evaluated, but calculated with as a formula.
+    ///Returns None for any other call.
+    pub(super) fn resolve_symbolic_call(&mut self, call_expr: &CallExpr) -> Option<Value> {
+        let arg_count = match call_expr.function_name.as_str() {
+            "derive" => 2,
+            "simplify" => 1,
+            _ => return None,
+        };
+        if self.scope.borrow().custom_function_exists(&call_expr.function_name) {
+            return None;
+        }
+        if call_expr.arguments.len() != arg_count {
+            return Some(self.add_error_value(errors::func_arg_wrong(&call_expr.function_name, call_expr.function_name_range.clone())));
+        }
+        let expression = match self.to_expression(&call_expr.arguments[0]) {
+            Ok(expression) => expression,
+            Err(error) => return Some(self.add_error_value(error)),
+        };
+        let result = if arg_count == 1 {
+            expression.simplify()
+        } else {
+            let var_node = &call_expr.arguments[1];
+            let NodeType::Id(id_expr) = &var_node.expr else {
+                return Some(self.add_error_value(errors::func_arg_wrong_type("derive", "Expected a variable.", var_node.get_range())));
+            };
+            match expression.derive(self.globals.get_text(&id_expr.id.range)) {
+                Ok(derivative) => derivative.simplify(),
+                Err(part) => return Some(self.add_error_value(errors::not_derivable(&part.to_string(), call_expr.arguments[0].get_range()))),
+            }
+        };
+        Some(Value::from_expression(result, call_expr.get_range()))
+    }
+
+    ///A variable that holds an expression is replaced by that expression. Any other id remains a symbol.
+    fn to_expression(&self, node: &Box<Node>) -> Result<Expression, Error> {
+        let not_supported = || errors::no_symbolic_expr(self.globals.get_text(&node.get_range()), node.get_range());
+        let expression = match &node.expr {
+            NodeType::Const(const_expr) => {
+                let ConstType::Numeric { number } = &const_expr.const_type else {
+                    return Err(not_supported());
+                };
+                Expression::Num(number.to_double())
+            },
+            NodeType::Id(id_expr) if id_expr.selector.is_none() => {
+                let id = self.globals.get_text(&id_expr.id.range);
+                match self.scope.borrow().get_variable(id) {
+                    Some(Value { variant: Variant::Expression { expression }, .. }) => expression,
+                    _ => Expression::Var(id.to_string()),
+                }
+            },
+            NodeType::Unary(unary_expr) if unary_expr.op.kind == TokenType::Min => Expression::Neg(Box::new(self.to_expression(&unary_expr.expr)?)),
+            NodeType::Binary(bin_expr) => {
+                //`foo(x)` with an unknown function is parsed as an implicit mult, which would hide the mistake.
+                if let (TokenType::Mult, "(", NodeType::Id(id_expr)) = (&bin_expr.op.kind, self.globals.get_text(&bin_expr.op.range), &bin_expr.expr1.expr) {
+                    let id = self.globals.get_text(&id_expr.id.range);
+                    if self.scope.borrow().get_variable(id).is_none() {
+                        return Err(errors::func_not_def(id, id_expr.id.range.clone()));
+                    }
+                }
+                let e1 = Box::new(self.to_expression(&bin_expr.expr1)?);
+                let e2 = Box::new(self.to_expression(&bin_expr.expr2)?);
+                match bin_expr.op.kind {
+                    TokenType::Plus => Expression::Add(e1, e2),
+                    TokenType::Min => Expression::Sub(e1, e2),
+                    TokenType::Mult => Expression::Mul(e1, e2),
+                    TokenType::Div => Expression::Div(e1, e2),
+                    TokenType::Power => Expression::Pow(e1, e2),
+                    _ => return Err(not_supported()),
+                }
+            },
+            NodeType::Call(call_expr) if call_expr.arguments.len() == 1 => {
+                Expression::Call(call_expr.function_name.clone(), Box::new(self.to_expression(&call_expr.arguments[0])?))
+            },
+            _ => return Err(not_supported()),
+        };
+        //a 'glued' id, as in `2x`, is parsed as a unit, but it's an implicit mult here.
+        if node.unit.is_empty() {
+            Ok(expression)
+        } else {
+            Ok(Expression::Mul(Box::new(expression), Box::new(Expression::Var(node.unit.id.clone()))))
+        }
+    }
+}
@@ -29,7 +29,7 @@ impl Unit {
 }
 
 #[derive(PartialEq)]
-pub enum UnitProperty { ANGLE, LENGTH, TEMP, MassWeight, DURATION, VOLUME, CURRENT, VOLTAGE, RESISTANCE, UNDEFINED }
+pub enum UnitProperty { ANGLE, LENGTH, TEMP, MassWeight, DURATION, VOLUME, CURRENT, VOLTAGE, RESISTANCE, Speed, Gravitation, Action, Entropy, PerAmount, UNDEFINED }
 
 #[derive(PartialEq)]
 pub enum UnitTag { DateTime, ShortDateTime, LongDateTime }
@@ -118,6 +118,23 @@ impl UnitsView {
         self.add_class(&UnitProperty::VOLUME, unit_defs);
         //electricity not set by default.
     }
+
+    ///The units of the physical constants, with `#define physics`.
+    pub fn add_physics_classes(&mut self, unit_defs: &HashMap<String, UnitDef>) {
+        self.add_class(&UnitProperty::Speed, unit_defs);
+        self.add_class(&UnitProperty::Gravitation, unit_defs);
+        self.add_class(&UnitProperty::Action, unit_defs);
+        self.add_class(&UnitProperty::Entropy, unit_defs);
+        self.add_class(&UnitProperty::PerAmount, unit_defs);
+    }
+
+    pub fn remove_physics_classes(&mut self, globals: &Globals) {
+        self.remove_class(&UnitProperty::Speed, globals);
+        self.remove_class(&UnitProperty::Gravitation, globals);
+        self.remove_class(&UnitProperty::Action, globals);
+        self.remove_class(&UnitProperty::Entropy, globals);
+        self.remove_class(&UnitProperty::PerAmount, globals);
+    }
 }
 
 pub fn default_to_si(def: &UnitDef, from: f64) -> f64 {
@@ -201,6 +218,13 @@ pub fn create_unit_defs() -> HashMap<String, UnitDef> {
     insert_def(&mut defs, "V", "V", 1.0, UnitProperty::VOLTAGE, &[]);
     insert_def(&mut defs, "mV", "V", 0.001, UnitProperty::VOLTAGE, &[]);
 
+    //the units of the physical constants.
+    insert_def(&mut defs, "m/s", "m/s", 1.0, UnitProperty::Speed, &[]);
+    insert_def(&mut defs, "m3/(kg*s2)", "m3/(kg*s2)", 1.0, UnitProperty::Gravitation, &[]);
+    insert_def(&mut defs, "J*s", "J*s", 1.0, UnitProperty::Action, &[]);
+    insert_def(&mut defs, "J/K", "J/K", 1.0, UnitProperty::Entropy, &[]);
+    insert_def(&mut defs, "/mol", "/mol", 1.0, UnitProperty::PerAmount, &[]);
+
     if let Some(def) = defs.get_mut("C") {
         def.to_si_fn = |_ud, d| d + 273.15;
         def.from_si_fn = |_ud, d| d - 273.15;
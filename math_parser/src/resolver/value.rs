@@ -1,5 +1,13 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::{Rc, Weak};
+use crate::complex::Complex;
 use crate::date::{Duration, Timepoint};
+use crate::expression::Expression;
+use crate::matrix::Matrix;
 use crate::number::Number;
+use crate::resolver::scope::Scope;
+use crate::resolver::unit::Unit;
 use crate::tokenizer::cursor::Range;
 
 #[derive(Clone)]
@@ -7,9 +15,16 @@ pub enum Variant {
     Numeric { number: Number },
     Date { date: Timepoint },
     Duration { duration: Duration },
+    Bool { value: bool },
+    Text { text: String },
     List { values: Vec<Value> },
+    Record { fields: Vec<(String, Value)> }, //named fields, in the order of definition.
+    Matrix { matrix: Matrix, unit: Unit }, //the values are in the SI unit, the same for all elements.
+    Complex { complex: Complex },
+    Expression { expression: Expression }, //unevaluated, as the result of a symbolic calculation.
     FunctionDef,
-    Comment, //echo comment
+    FunctionRef { name: String, scope: Weak<RefCell<Scope>>, captures: HashMap<String, Value> }, //a named function or a lambda, as a value.
+    Comment { text: String }, //echo comment
     Last, // used for dates.
     Error,
     Define,
@@ -17,7 +32,8 @@ pub enum Variant {
 }
 
 #[repr(u8)]
-pub enum OperandType { Number, Date, Duration, Invalid }
+#[derive(Copy, Clone)]
+pub enum OperandType { Number, Date, Duration, Bool, Text, List, Matrix, Complex, Invalid }
 
 impl Variant {
     pub fn name(&self) -> &'static str {
@@ -25,9 +41,16 @@ impl Variant {
             Variant::Numeric {..} => "Number",
             Variant::Date {..} => "Timepoint",
             Variant::Duration {..} => "Duration",
+            Variant::Bool {..} => "Bool",
+            Variant::Text {..} => "Text",
             Variant::List {..} => "List",
+            Variant::Record {..} => "Record",
+            Variant::Matrix {..} => "Matrix",
+            Variant::Complex {..} => "Complex",
+            Variant::Expression {..} => "Expression",
             Variant::FunctionDef => "FunctionDef",
-            Variant::Comment  => "Comment",
+            Variant::FunctionRef {..} => "FunctionRef",
+            Variant::Comment {..} => "Comment",
             Variant::Last  => "Last",
             Variant::Error  => "Error",
             Variant::Define  => "Define",
@@ -40,6 +63,11 @@ impl Variant {
             Variant::Numeric {..} => OperandType::Number,
             Variant::Date {..} => OperandType::Date,
             Variant::Duration {..} => OperandType::Duration,
+            Variant::Bool {..} => OperandType::Bool,
+            Variant::Text {..} => OperandType::Text,
+            Variant::List {..} => OperandType::List,
+            Variant::Matrix {..} => OperandType::Matrix,
+            Variant::Complex {..} => OperandType::Complex,
             _  => OperandType::Invalid,
         }
     }
@@ -51,6 +79,7 @@ pub struct Value {
     pub stmt_range: Range,
     pub variant: Variant,
     pub has_errors: bool,
+    pub echo: Option<Range>, //the echoed source text of the statement.
 }
 
 impl Value {
@@ -60,6 +89,7 @@ impl Value {
             stmt_range: range,
             variant: Variant::Error,
             has_errors: true,
+            echo: None,
         }
     }
     pub fn none(range: Range) -> Self {
@@ -68,6 +98,7 @@ impl Value {
             stmt_range: range,
             variant: Variant::None,
             has_errors: false,
+            echo: None,
         }
     }
 
@@ -77,6 +108,7 @@ impl Value {
             stmt_range: range,
             variant: Variant::Last,
             has_errors: false,
+            echo: None,
         }
     }
 
@@ -86,6 +118,7 @@ impl Value {
             stmt_range: range,
             variant: Variant::Numeric {number: value},
             has_errors: false,
+            echo: None,
         }
     }
 
@@ -95,6 +128,51 @@ impl Value {
             stmt_range: range,
             variant: Variant::List { values },
             has_errors: false,
+            echo: None,
+        }
+    }
+
+    pub fn from_record(fields: Vec<(String, Value)>, range: Range) -> Self {
+        Value {
+            id: None,
+            stmt_range: range,
+            variant: Variant::Record { fields },
+            has_errors: false,
+            echo: None,
+        }
+    }
+
+    pub fn from_matrix(matrix: Matrix, unit: Unit, range: Range) -> Self {
+        Value {
+            id: None,
+            stmt_range: range,
+            variant: Variant::Matrix { matrix, unit },
+            has_errors: false,
+            echo: None,
+        }
+    }
+
+    ///A complex number without an imaginary part is just a number, as in `(1+2i)*(1-2i)`.
+    pub fn from_complex(complex: Complex, range: Range) -> Self {
+        if complex.is_real() {
+            return Value::from_number(Number::from(complex.re), range);
+        }
+        Value {
+            id: None,
+            stmt_range: range,
+            variant: Variant::Complex { complex },
+            has_errors: false,
+            echo: None,
+        }
+    }
+
+    pub fn from_expression(expression: Expression, range: Range) -> Self {
+        Value {
+            id: None,
+            stmt_range: range,
+            variant: Variant::Expression { expression },
+            has_errors: false,
+            echo: None,
         }
     }
 
@@ -105,6 +183,7 @@ impl Value {
             stmt_range: range,
             variant: Variant::Date {date },
             has_errors,
+            echo: None,
         }
     }
 
@@ -114,6 +193,38 @@ impl Value {
             stmt_range: range,
             variant: Variant::Duration {duration},
             has_errors: false,
+            echo: None,
+        }
+    }
+
+    pub fn from_bool(value: bool, range: Range) -> Self {
+        Value {
+            id: None,
+            stmt_range: range,
+            variant: Variant::Bool {value},
+            has_errors: false,
+            echo: None,
+        }
+    }
+
+    pub fn from_text(text: String, range: Range) -> Self {
+        Value {
+            id: None,
+            stmt_range: range,
+            variant: Variant::Text {text},
+            has_errors: false,
+            echo: None,
+        }
+    }
+
+    ///The captures are the variables of the function call where a lambda is created.
+    pub fn from_function_ref(name: String, scope: &Rc<RefCell<Scope>>, captures: HashMap<String, Value>, range: Range) -> Self {
+        Value {
+            id: None,
+            stmt_range: range,
+            variant: Variant::FunctionRef { name, scope: Rc::downgrade(scope), captures },
+            has_errors: false,
+            echo: None,
         }
     }
 
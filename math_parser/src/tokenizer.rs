@@ -52,10 +52,50 @@ impl Cursor<'_> {
             '[' => BracOpen,
             ']' => BracClose,
             '^' => Power,
-            '=' => Eq,
+            '=' => {
+                match self.peek() {
+                    '=' => { self.next(); EqEq },
+                    '>' => { self.next(); Arrow },
+                    _ => Eq
+                }
+            },
+            '<' => {
+                if self.peek() == '=' {
+                    self.next();
+                    LessEq
+                } else {
+                    Less
+                }
+            },
+            '>' => {
+                if self.peek() == '=' {
+                    self.next();
+                    GreaterEq
+                } else {
+                    Greater
+                }
+            },
+            '$' => {
+                if self.peek().is_ascii_digit() {
+                    self.eat_while(|c| c.is_ascii_digit());
+                    LineRef
+                } else {
+                    Unknown
+                }
+            },
+            '~' => {
+                if self.peek() == '=' {
+                    self.next();
+                    Approx
+                } else {
+                    Unknown
+                }
+            },
             ',' => Comma,
             '|' => Pipe,
             ';' => SemiColon,
+            '?' => Question,
+            ':' => Colon,
             '%' => {
                 if self.peek() == '%' {
                     self.next(); //eat
@@ -69,9 +109,18 @@ impl Cursor<'_> {
                     ('/', '/') => {
                         self.next();
                         self.next();
-                        self.get_to_eol();
-                        start_pos += 3; //remove the "!//" from the string.
-                        EchoCommentLine
+                        if self.peek() == '/' { //the rest of the line is a comment.
+                            self.get_to_eol();
+                            EchoStart
+                        } else {
+                            self.get_to_eol();
+                            start_pos += 3; //remove the "!//" from the string.
+                            EchoCommentLine
+                        }
+                    },
+                    ('=', _) => {
+                        self.next();
+                        NotEq
                     },
                     _ => Exclam
                 }
@@ -127,6 +176,9 @@ impl Cursor<'_> {
                 if self.match_word("pragma") {
                     break 'hash_token Pragma;
                 }
+                if self.match_word("include") {
+                    break 'hash_token Include;
+                }
                 if self.peek() == '/' {
                     self.next();
                     break 'hash_token MuteEnd;
@@ -140,9 +192,15 @@ impl Cursor<'_> {
                         EqDiv
                     },
                     '/' => {
-                        self.get_to_eol();
-                        // CommentLine
-                        return self.next_token();
+                        self.next();
+                        if let ('/', '!') = (self.peek(), self.peek_second()) { //the rest of the line is a comment.
+                            self.get_to_eol();
+                            EchoEnd
+                        } else {
+                            self.get_to_eol();
+                            // CommentLine
+                            return self.next_token();
+                        }
                     },
                     '*' => {
                         self.get_to_end_of_comment();
@@ -166,6 +224,17 @@ impl Cursor<'_> {
                 #[cfg(not(debug_assertions))]
                 { return Token::new(QuotedStr, self.source.index, start_pos, end_pos); }
             },
+            '"' => {
+                start_pos += 1; //exclude the quote from the range.
+                self.eat_while(|c| c != '"');
+                let end_pos = self.get_pos(); //end pos without quote.
+                self.next(); //eat end quote, if any. (eot?)
+
+                #[cfg(debug_assertions)]
+                { return Token::new(DoubleQuotedStr, self.source.index, start_pos, end_pos, self.source.get_text()[start_pos..end_pos].to_string()); }
+                #[cfg(not(debug_assertions))]
+                { return Token::new(DoubleQuotedStr, self.source.index, start_pos, end_pos); }
+            },
             c @ ('0'..='9') => {
                 self.number = self.parse_number(c);
                 Number
@@ -175,6 +244,12 @@ impl Cursor<'_> {
                 let id = &self.source.get_text()[start_pos..self.get_pos()];
                 match id {
                     "function" => Function,
+                    "const" => Const,
+                    "if" => If,
+                    "else" => Else,
+                    "step" => Step,
+                    "within" => Within,
+                    "_" => LastResult,
                     _ => Id
                 }
             },
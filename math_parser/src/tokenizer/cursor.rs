@@ -81,6 +81,16 @@ impl<'a> Cursor<'a> {
         }
     }
 
+    ///A cursor over a part of the source. Positions stay relative to the whole source.
+    pub fn for_range<'r>(source: &'r Source, range: &Range) -> Cursor<'r> {
+        let mut cursor = Cursor::new(source);
+        cursor.chars = source.get_text()[range.start..range.end].chars();
+        cursor.len_text = range.end;
+        cursor.newline_found = false;
+        cursor.is_beginning_of_text = false;
+        cursor
+    }
+
     pub fn peek(&self) -> char {
         self.chars.clone().next().unwrap_or(EOF_CHAR)
     }
@@ -2,7 +2,7 @@ use crate::errors::Error;
 use crate::globals::SourceIndex;
 use crate::globals::sources::Source;
 use crate::number::Number;
-use crate::tokenizer::cursor::Cursor;
+use crate::tokenizer::cursor::{Cursor, Range};
 use crate::tokenizer::Token;
 
 #[derive(Clone)]
@@ -15,7 +15,14 @@ pub struct PeekingTokenizer<'a> {
 
 impl<'a> PeekingTokenizer<'a> {
     pub fn new(source: &'a Source) -> Self {
-        let mut cur =  Cursor::new(source);
+        Self::from_cursor(Cursor::new(source))
+    }
+
+    pub fn for_range(source: &'a Source, range: &Range) -> Self {
+        Self::from_cursor(Cursor::for_range(source, range))
+    }
+
+    fn from_cursor(mut cur: Cursor<'a>) -> Self {
         let prev_cur = cur.clone();
         let current_number = cur.number.clone(); //before setting peeked_token!
         let peeked_token = cur.next_token();
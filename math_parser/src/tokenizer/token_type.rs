@@ -22,9 +22,19 @@ pub enum TokenType {
     EqUnit,
     //keep above together!
 
+    Less,
+    Greater,
+    LessEq,
+    GreaterEq,
+    EqEq,
+    NotEq,
+    Approx, // ~=
+
     Number,
     Power,
     Id,
+    LastResult, // _
+    LineRef, // $3
     SemiColon,
     Comma,
     Dot, //except for the decimal dot.
@@ -32,18 +42,30 @@ pub enum TokenType {
     Pipe,
     Exclam,
     EchoCommentLine,
+    EchoStart, // !///
+    EchoEnd, // ///!
     MuteLine,
     MuteStart,
     MuteEnd,
     QuotedStr,
+    DoubleQuotedStr, // "text"
     Newline,
     Function,
+    Const,
+    If,
+    Else,
+    Step,
+    Within,
+    Question,
+    Arrow, // =>
+    Colon,
     Percent,
     Modulo,
 
     Define,
     Undef,
     Pragma,
+    Include,
 
     Unknown,
     Eot,
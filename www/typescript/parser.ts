@@ -89,10 +89,28 @@ function formatResult (line: ResultLine) {
     else if (line.type === "List" || line.type === "L") {
         strFormatted = "(" + formatList(line.list) + ")";
     }
+    else if (line.type === "Record") {
+        strFormatted = "{" + Object.entries(line.record).map(([name, field]) => name + ": " + formatResult(field)).join(", ") + "}";
+    }
+    else if (line.type === "Matrix") {
+        strFormatted = "[" + line.matrix.map(row => row.join(", ")).join("; ") + "]";
+    }
+    else if (line.type === "Complex") {
+        strFormatted = line.complex;
+    }
+    else if (line.type === "Expression") {
+        strFormatted = line.expression;
+    }
+    else if (line.type === "Bool") {
+        strFormatted = line.bool ? "true" : "false";
+    }
+    else if (line.type === "Text") {
+        strFormatted = line.text;
+    }
     else if (line.type === "Last") {
         strFormatted = "'last'";
     }
-    else if (line.type === "FunctionDef") {
+    else if (line.type === "FunctionDef" || line.type === "FunctionRef") {
         strFormatted = "Function";
     }
     return strFormatted;
@@ -116,6 +134,9 @@ function linetoResultString (line: ResultLine, errors: ErrorResult[], showErrors
     }
     else
         strLine += formatResult(line);
+    if (line.echo) {
+        strLine += " " + line.echo;
+    }
 
     return strLine;
 }
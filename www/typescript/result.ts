@@ -31,11 +31,19 @@ export interface ResultLine {
     src: number,
     line: number,
     id: string,
+    echo: string,
     date: DateResult,
     duration: DurationResult,
     comment: string,
     number: NumberResult,
+    bool: boolean,
+    text: string,
+    function: string,
     list: ResultLine[],
+    record: { [name: string]: ResultLine },
+    matrix: string[][],
+    complex: string,
+    expression: string,
 }
 
 export interface Results {